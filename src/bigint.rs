@@ -0,0 +1,321 @@
+// Reference implementation of the bigint arithmetic of the runtime, bigint_add, bigint_sub,
+// bigint_mul, bigint_div and bigint_neg. The compiler uses it to fold constant bigint expressions,
+// comparisons and conversions of a bigint are only done by the runtime.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+const BASE: u64 = 1_000_000_000;
+
+/// Arbitrary-precision integer: a sign and the magnitude in base 10^9 digits, least significant
+/// first. There are no leading zero digits, so zero has no digits and is never negative.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BigInt {
+    negative: bool,
+    digits: Vec<u32>,
+}
+
+impl BigInt {
+    fn new(negative: bool, mut digits: Vec<u32>) -> BigInt {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        BigInt {
+            negative: negative && !digits.is_empty(),
+            digits,
+        }
+    }
+
+    pub fn from_u64(mut v: u64) -> BigInt {
+        let mut digits = Vec::new();
+        while v != 0 {
+            digits.push((v % BASE) as u32);
+            v /= BASE;
+        }
+        BigInt::new(false, digits)
+    }
+
+    /// Parses decimal digits with an optional minus sign
+    pub fn parse(s: &str) -> Option<BigInt> {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        if s.is_empty() || !s.bytes().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let mut digits = Vec::new();
+        let mut end = s.len();
+        while end > 0 {
+            let start = end.saturating_sub(9);
+            digits.push(s[start..end].parse().unwrap());
+            end = start;
+        }
+        Some(BigInt::new(negative, digits))
+    }
+
+    /// Quotient rounded toward zero, None when dividing by zero
+    pub fn checked_div(&self, other: &BigInt) -> Option<BigInt> {
+        if other.digits.is_empty() {
+            return None;
+        }
+
+        // Long division, every quotient digit is found by a binary search
+        let mut quotient = vec![0; self.digits.len()];
+        let mut rest = BigInt::from_u64(0);
+        let divisor = BigInt::new(false, other.digits.clone());
+        for i in (0..self.digits.len()).rev() {
+            let mut digits = vec![self.digits[i]];
+            digits.extend_from_slice(&rest.digits);
+            rest = BigInt::new(false, digits);
+
+            let (mut low, mut high) = (0, BASE - 1);
+            while low < high {
+                let mid = (low + high).div_ceil(2);
+                if compare(&(&divisor * &BigInt::from_u64(mid)).digits, &rest.digits)
+                    == Ordering::Greater
+                {
+                    high = mid - 1;
+                } else {
+                    low = mid;
+                }
+            }
+            quotient[i] = low as u32;
+            rest = &rest - &(&divisor * &BigInt::from_u64(low));
+        }
+        Some(BigInt::new(self.negative != other.negative, quotient))
+    }
+}
+
+// Compares magnitudes
+fn compare(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut digits = Vec::new();
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let sum = carry + *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64;
+        digits.push((sum % BASE) as u32);
+        carry = sum / BASE;
+    }
+    digits.push(carry as u32);
+    digits
+}
+
+// The magnitude a has to be at least b
+fn sub_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut digits = Vec::new();
+    let mut borrow = 0;
+    for (i, a) in a.iter().enumerate() {
+        let mut d = *a as i64 - borrow - *b.get(i).unwrap_or(&0) as i64;
+        borrow = 0;
+        if d < 0 {
+            d += BASE as i64;
+            borrow = 1;
+        }
+        digits.push(d as u32);
+    }
+    digits
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_magnitudes(&self.digits, &other.digits));
+        }
+        match compare(&self.digits, &other.digits) {
+            Ordering::Less => {
+                BigInt::new(other.negative, sub_magnitudes(&other.digits, &self.digits))
+            }
+            _ => BigInt::new(self.negative, sub_magnitudes(&self.digits, &other.digits)),
+        }
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.digits.clone())
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &(-other)
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        let mut digits = vec![0u64; self.digits.len() + other.digits.len() + 1];
+        for (i, a) in self.digits.iter().enumerate() {
+            let mut carry = 0;
+            for (j, b) in other.digits.iter().enumerate() {
+                let d = digits[i + j] + *a as u64 * *b as u64 + carry;
+                digits[i + j] = d % BASE;
+                carry = d / BASE;
+            }
+            digits[i + other.digits.len()] += carry;
+        }
+        BigInt::new(
+            self.negative != other.negative,
+            digits.into_iter().map(|d| d as u32).collect(),
+        )
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.digits.split_last() {
+            None => write!(f, "0"),
+            Some((last, rest)) => {
+                if self.negative {
+                    write!(f, "-")?;
+                }
+                write!(f, "{}", last)?;
+                for d in rest.iter().rev() {
+                    write!(f, "{:09}", d)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(s: &str) -> BigInt {
+        BigInt::parse(s).unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        assert_eq!(big("0").to_string(), "0");
+        assert_eq!(big("-0").to_string(), "0");
+        assert_eq!(big("000123").to_string(), "123");
+        assert_eq!(big("-1000000000").to_string(), "-1000000000");
+        assert_eq!(
+            big("123456789012345678901234567890").to_string(),
+            "123456789012345678901234567890"
+        );
+        // Inner digits are padded to nine places
+        assert_eq!(
+            big("5000000000000000007").to_string(),
+            "5000000000000000007"
+        );
+        assert_eq!(big("-0"), BigInt::from_u64(0));
+        assert_eq!(BigInt::parse(""), None);
+        assert_eq!(BigInt::parse("-"), None);
+        assert_eq!(BigInt::parse("12a"), None);
+        assert_eq!(BigInt::parse("+1"), None);
+    }
+
+    #[test]
+    fn from_u64() {
+        assert_eq!(BigInt::from_u64(0).to_string(), "0");
+        assert_eq!(BigInt::from_u64(u64::MAX).to_string(), u64::MAX.to_string());
+    }
+
+    #[test]
+    fn add_carries_across_digits() {
+        assert_eq!((&big("999999999") + &big("1")).to_string(), "1000000000");
+        assert_eq!(
+            (&big("999999999999999999") + &big("1")).to_string(),
+            "1000000000000000000"
+        );
+        assert_eq!(
+            (&big("1") + &big("999999999999999999")).to_string(),
+            "1000000000000000000"
+        );
+    }
+
+    #[test]
+    fn add_and_sub_with_signs() {
+        assert_eq!((&big("-5") + &big("3")).to_string(), "-2");
+        assert_eq!((&big("5") + &big("-3")).to_string(), "2");
+        assert_eq!((&big("-5") + &big("-3")).to_string(), "-8");
+        assert_eq!((&big("5") + &big("-5")).to_string(), "0");
+        assert_eq!((&big("3") - &big("5")).to_string(), "-2");
+        assert_eq!((&big("-3") - &big("-5")).to_string(), "2");
+        assert_eq!((&big("-3") - &big("5")).to_string(), "-8");
+        assert_eq!((&big("5") - &big("5")), BigInt::from_u64(0));
+        assert_eq!((-&big("0")).to_string(), "0");
+    }
+
+    #[test]
+    fn sub_borrows_across_digits() {
+        assert_eq!((&big("1000000000") - &big("1")).to_string(), "999999999");
+        assert_eq!(
+            (&big("1000000000000000000") - &big("1")).to_string(),
+            "999999999999999999"
+        );
+        assert_eq!(
+            (&big("1") - &big("1000000000000000000")).to_string(),
+            "-999999999999999999"
+        );
+    }
+
+    #[test]
+    fn mul() {
+        assert_eq!(
+            (&big("999999999") * &big("999999999")).to_string(),
+            "999999998000000001"
+        );
+        assert_eq!(
+            (&big("123456789012345678901234567890") * &big("-987654321")).to_string(),
+            "-121932631124828532112482853211126352690"
+        );
+        assert_eq!((&big("-7") * &big("-6")).to_string(), "42");
+        assert_eq!((&big("-7") * &big("0")).to_string(), "0");
+        let x = big("18446744073709551616");
+        assert_eq!(
+            (&x * &x).to_string(),
+            "340282366920938463463374607431768211456"
+        );
+    }
+
+    #[test]
+    fn div_rounds_toward_zero() {
+        let div = |a: &str, b: &str| big(a).checked_div(&big(b)).unwrap().to_string();
+        assert_eq!(div("7", "2"), "3");
+        assert_eq!(div("-7", "2"), "-3");
+        assert_eq!(div("7", "-2"), "-3");
+        assert_eq!(div("-7", "-2"), "3");
+        assert_eq!(div("1", "2"), "0");
+        assert_eq!(div("-1", "2"), "0");
+        assert_eq!(div("1000000000000000000", "1000000000"), "1000000000");
+        assert_eq!(
+            div(
+                "340282366920938463463374607431768211456",
+                "18446744073709551616"
+            ),
+            "18446744073709551616"
+        );
+        assert_eq!(
+            div("121932631124828532112482853211126352690", "987654321"),
+            "123456789012345678901234567890"
+        );
+    }
+
+    #[test]
+    fn div_by_zero() {
+        assert_eq!(big("5").checked_div(&big("0")), None);
+        assert_eq!(big("0").checked_div(&big("-0")), None);
+        assert_eq!(big("0").checked_div(&big("5")), Some(BigInt::from_u64(0)));
+    }
+}
@@ -1,7 +1,10 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
+use crate::bigint::BigInt;
 use crate::lexer::*;
+use crate::symbols::SymbolId;
+use crate::verify::{annotated, Command, Term};
 
 macro_rules! unreachable {
     () => {
@@ -9,11 +12,41 @@ macro_rules! unreachable {
     };
 }
 
-fn error(s: &str, line: u32) -> ! {
+pub fn error(s: &str, line: u32) -> ! {
     println!("near line {}: {}", line, s);
     std::process::exit(0);
 }
 
+pub fn warning(s: &str, line: u32) {
+    eprintln!("near line {}: warning: {}", line, s);
+}
+
+/// Type errors with the lines they are on, see ExprAble::type_check
+pub type TypeErrors = Vec<(u32, String)>;
+
+/// Checks the types of a parsed module, reports all errors in the order of their lines and stops
+/// if there are any. The name of an imported module is given so its errors can be told apart.
+pub fn type_check(s: &dyn StmtAble, module: Option<&str>) {
+    let mut errors = Vec::new();
+    s.type_check(&mut errors);
+    if errors.is_empty() {
+        return;
+    }
+    errors.sort_by_key(|(line, _)| *line);
+    for (line, e) in errors {
+        match module {
+            Some(m) => println!("near line {} in module {}: {}", line, m, e),
+            None => println!("near line {}: {}", line, e),
+        }
+    }
+    std::process::exit(0);
+}
+
+// An error about an operand of the invalid type has been reported already
+fn valid(types: &[&TypeBase]) -> bool {
+    types.iter().all(|p| **p != type_invalid())
+}
+
 pub fn emit_label(i: u32) {
     print!("L{}:", i);
 }
@@ -32,6 +65,68 @@ pub trait ExprAble {
     fn to_string(&self) -> String;
 
     fn get_type(&self) -> &TypeBase;
+
+    // Assignable expressions, variables and dereferenced pointers, implement store. It emits
+    // the assignment of x and returns the assigned value.
+
+    fn is_lvalue(&self) -> bool {
+        false
+    }
+
+    fn store(&self, _x: &dyn ExprAble) -> Box<dyn ExprAble> {
+        unreachable!();
+    }
+
+    // Named variables, the only operands of &
+
+    fn is_variable(&self) -> bool {
+        false
+    }
+
+    // Value of an integer literal, see adopt
+
+    fn literal(&self) -> Option<u64> {
+        None
+    }
+
+    // -n for an integer literal n retyped as p when -n fits in it, see adopt
+
+    fn negated(&self, _p: &TypeBase) -> Option<Box<dyn ExprAble>> {
+        None
+    }
+
+    // Value of a bigint constant, constant bigint expressions are folded at compile time
+
+    fn big_literal(&self) -> Option<BigInt> {
+        None
+    }
+
+    fn is_constant(&self) -> bool {
+        false
+    }
+
+    // A tuple literal hands its elements over to the assignment it is the right side of
+
+    fn elements(&mut self) -> Option<Vec<Box<dyn ExprAble>>> {
+        None
+    }
+
+    // The verifier reads an expression as a term and an assignment as the pairs of locations
+    // and values it assigns, see verify.rs
+
+    fn term(&self) -> Term {
+        Term::Unsupported(self.to_string())
+    }
+
+    fn assignments(&self) -> Option<Vec<(Term, Term)>> {
+        None
+    }
+
+    // The type checker walks the parsed program, a node checks its operands and then itself.
+    // Constructors give a node that fails its check the invalid type, so the node reports the
+    // error when its operands are valid.
+
+    fn type_check(&self, _errors: &mut TypeErrors) {}
 }
 
 #[derive(Clone)]
@@ -131,13 +226,14 @@ macro_rules! get_type {
     }
 }
 
+#[derive(Clone)]
 struct Temp {
     expr_base: ExprBase,
-    number: u8,
+    number: u32,
 }
 
 impl Temp {
-    fn new(p: TypeBase, temp_count: Rc<RefCell<u8>>) -> Temp {
+    fn new(p: TypeBase, temp_count: Rc<RefCell<u32>>) -> Temp {
         {
             let mut reference = temp_count.borrow_mut();
             *reference = *reference + 1;
@@ -158,15 +254,24 @@ impl Temp {
 }
 
 impl ExprAble for Temp {
+    fn gen(&self) -> Box<dyn ExprAble> {
+        Box::new(self.clone())
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        Box::new(self.clone())
+    }
+
+    fn jumping(&self, t: u32, f: u32) {
+        self.emit_jumps(self.to_string(), t, f);
+    }
+
     fn to_string(&self) -> String {
         format!("t{}", self.number)
     }
 
     // Explicitly inherited:
 
-    gen! {self, expr_base}
-    reduce! {self, expr_base}
-    jumping! {self, expr_base}
     emit_jumps! {self, expr_base}
     get_type! {self, expr_base}
 }
@@ -174,23 +279,61 @@ impl ExprAble for Temp {
 #[derive(Clone)]
 pub struct Id {
     expr_base: ExprBase,
-    offset: u32,
+    offset: Rc<Cell<u32>>, // set by the frame layout once the program is parsed
+    symbol: SymbolId,      // declaration of the variable in the scope tree
 }
 
 impl Id {
-    pub fn new(id: WordBase, p: TypeBase, b: u32) -> Id {
+    pub fn new(id: WordBase, p: TypeBase, symbol: SymbolId) -> Id {
         Id {
             expr_base: ExprBase::new(Token::Word(Word::Word(id)), p),
-            offset: b,
+            offset: Rc::new(Cell::new(0)),
+            symbol,
         }
     }
+
+    pub fn offset(&self) -> u32 {
+        self.offset.get()
+    }
+
+    /// Places the variable in the frame, every copy of the Id sees the offset
+    pub fn place(&self, offset: u32) {
+        self.offset.set(offset);
+    }
 }
 
 impl ExprAble for Id {
-    // All explicitly inherited
+    fn is_lvalue(&self) -> bool {
+        true
+    }
+
+    fn is_variable(&self) -> bool {
+        true
+    }
+
+    fn store(&self, x: &dyn ExprAble) -> Box<dyn ExprAble> {
+        emit(format!("{} = {}", self.to_string(), x.gen().to_string()));
+        Box::new(self.clone())
+    }
+
+    fn gen(&self) -> Box<dyn ExprAble> {
+        Box::new(self.clone())
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        Box::new(self.clone())
+    }
+
+    fn term(&self) -> Term {
+        let p = self.get_type();
+        if *p != type_int() && *p != type_bool() {
+            return Term::Unsupported(format!("{} of type {}", self.to_string(), p.word.lexeme));
+        }
+        Term::Var(self.to_string(), self.symbol, *p == type_bool())
+    }
+
+    // Explicitly inherited:
 
-    gen! {self, expr_base}
-    reduce! {self, expr_base}
     jumping! {self, expr_base}
     emit_jumps! {self, expr_base}
     to_string! {self, expr_base}
@@ -199,11 +342,11 @@ impl ExprAble for Id {
 
 struct OpBase {
     expr_base: ExprBase,
-    pub temp_count: Rc<RefCell<u8>>,
+    pub temp_count: Rc<RefCell<u32>>,
 }
 
 impl OpBase {
-    pub fn new(tok: Token, p: TypeBase, count: Rc<RefCell<u8>>) -> OpBase {
+    pub fn new(tok: Token, p: TypeBase, count: Rc<RefCell<u32>>) -> OpBase {
         OpBase {
             expr_base: ExprBase::new(tok, p),
             temp_count: count,
@@ -214,6 +357,10 @@ impl OpBase {
 macro_rules! op_reduce {
     ( $self:expr ) => {{
         let x = $self.gen();
+        // A folded bigint constant needs no temporary
+        if (*x).big_literal().is_some() {
+            return x;
+        }
         let t = Box::new(Temp::new(
             (*$self.get_type()).clone(),
             $self.temp_count.clone(),
@@ -223,6 +370,26 @@ macro_rules! op_reduce {
     }};
 }
 
+/// Emits the arguments of a call of a runtime function, they have to be reduced already, and
+/// returns the call
+fn call(func: &str, args: Vec<Box<dyn ExprAble>>, p: TypeBase) -> Box<dyn ExprAble> {
+    for x in args.iter() {
+        emit(format!("param {}", (*x).to_string()));
+    }
+    let name = format!("call {}, {}", func, args.len());
+    Box::new(ExprBase::new(
+        Token::Word(Word::Word(WordBase::new(name, Tag::Id as u32))),
+        p,
+    ))
+}
+
+fn big_constant(v: BigInt) -> Box<dyn ExprAble> {
+    Box::new(Constant::new(
+        Token::BigNum(BigNum::new(v.to_string())),
+        type_bigint(),
+    ))
+}
+
 impl ExprAble for OpBase {
     fn reduce(&self) -> Box<dyn ExprAble> {
         op_reduce!(self)
@@ -242,7 +409,7 @@ pub struct Arith {
     expr1: Box<dyn ExprAble>,
     expr2: Box<dyn ExprAble>,
     line: u32,
-    temp_count: Rc<RefCell<u8>>,
+    temp_count: Rc<RefCell<u32>>,
 }
 
 impl Arith {
@@ -251,34 +418,133 @@ impl Arith {
         x1: Box<dyn ExprAble>,
         x2: Box<dyn ExprAble>,
         line: u32,
-        count: Rc<RefCell<u8>>,
+        count: Rc<RefCell<u32>>,
     ) -> Arith {
-        let type1 = (*x1).get_type();
-        let type2 = (*x2).get_type();
-        match TypeBase::max(type1, type2) {
-            Some(type_base) => {
+        let (x1, x2) = adopt_pair(x1, x2);
+        let tag = tok.get_tag().unwrap();
+        let p = match Arith::check(tag, (*x1).get_type(), (*x2).get_type()) {
+            Some(p) => p,
+            None => {
                 return Arith {
-                    op_base: OpBase::new(tok, type_base, count.clone()),
+                    op_base: OpBase::new(tok, type_invalid(), count.clone()),
                     expr1: x1,
                     expr2: x2,
-                    line: line,
-                    temp_count: count.clone(),
-                };
+                    line,
+                    temp_count: count,
+                }
             }
-            None => error("type error", line),
         };
+
+        // Pointer arithmetic: the integer operand counts elements so it is scaled by the width
+        // of the pointee
+        if let Some(of) = p.pointee() {
+            let (ptr, n) = if (*x1).get_type().pointee().is_some() {
+                (x1, x2)
+            } else {
+                (x2, x1)
+            };
+            let offset = Arith::scale(n, of, line, count.clone());
+            return Arith {
+                op_base: OpBase::new(tok, p, count.clone()),
+                expr1: ptr,
+                expr2: offset,
+                line,
+                temp_count: count,
+            };
+        }
+
+        Arith {
+            expr1: widen(x1, &p, count.clone()),
+            expr2: widen(x2, &p, count.clone()),
+            op_base: OpBase::new(tok, p, count.clone()),
+            line,
+            temp_count: count,
+        }
+    }
+
+    /// Returns the type of p1 op p2, an integer added to or subtracted from a pointer keeps the
+    /// pointer type
+    pub fn check(tag: u32, p1: &TypeBase, p2: &TypeBase) -> Option<TypeBase> {
+        if (tag == '+' as u32 || tag == '-' as u32) && p1.pointee().is_some() && integral(p2) {
+            Some(p1.clone())
+        } else if tag == '+' as u32 && integral(p1) && p2.pointee().is_some() {
+            Some(p2.clone())
+        } else {
+            TypeBase::max(p1, p2)
+        }
+    }
+
+    // Verb of an arithmetic operator in a type error
+    fn verb(tag: u32) -> &'static str {
+        match char::from_u32(tag) {
+            Some('+') => "add",
+            Some('-') => "subtract",
+            Some('*') => "multiply",
+            _ => "divide",
+        }
+    }
+
+    // Arithmetic on bigints is a runtime call unless both operands are constants
+    fn big(&self) -> Box<dyn ExprAble> {
+        let x1 = (*self.expr1).reduce();
+        let x2 = (*self.expr2).reduce();
+        let tag = self.op_base.expr_base.op.get_tag().unwrap();
+        let func = match char::from_u32(tag) {
+            Some('+') => "bigint_add",
+            Some('-') => "bigint_sub",
+            Some('*') => "bigint_mul",
+            _ => "bigint_div",
+        };
+
+        if let (Some(a), Some(b)) = ((*x1).big_literal(), (*x2).big_literal()) {
+            return big_constant(match func {
+                "bigint_add" => &a + &b,
+                "bigint_sub" => &a - &b,
+                "bigint_mul" => &a * &b,
+                _ => match a.checked_div(&b) {
+                    Some(v) => v,
+                    None => error("division by zero", self.line),
+                },
+            });
+        }
+        call(func, vec![x1, x2], type_bigint())
+    }
+
+    fn scale(
+        x: Box<dyn ExprAble>,
+        of: &TypeBase,
+        line: u32,
+        count: Rc<RefCell<u32>>,
+    ) -> Box<dyn ExprAble> {
+        let width = Constant::new(Token::Num(Num::new(of.get_width() as u64)), type_int());
+        Box::new(Arith::new(
+            Token::Token(TokenBase { tag: '*' as u32 }),
+            x,
+            Box::new(width),
+            line,
+            count,
+        ))
     }
 }
 
 impl ExprAble for Arith {
     fn gen(&self) -> Box<dyn ExprAble> {
-        Box::new(Arith::new(
-            self.op_base.expr_base.op.clone(),
-            self.expr1.reduce(),
-            self.expr2.reduce(),
-            self.line,
-            self.op_base.temp_count.clone(),
-        ))
+        if *self.get_type() == type_bigint() {
+            return self.big();
+        }
+
+        // Operands are already typed and scaled so the node is rebuilt without Arith::new
+        Box::new(Arith {
+            op_base: OpBase::new(
+                self.op_base.expr_base.op.clone(),
+                self.get_type().clone(),
+                self.op_base.temp_count.clone(),
+            ),
+            expr1: self.expr1.reduce(),
+            expr2: self.expr2.reduce(),
+            line: self.line,
+            temp_count: self.temp_count.clone(),
+        })
     }
 
     fn to_string(&self) -> String {
@@ -290,6 +556,36 @@ impl ExprAble for Arith {
         )
     }
 
+    fn term(&self) -> Term {
+        let op = self.op_base.expr_base.op.get_tag().unwrap();
+        match char::from_u32(op) {
+            Some(c @ ('+' | '-' | '*')) if *self.get_type() == type_int() => Term::Arith(
+                c,
+                Box::new((*self.expr1).term()),
+                Box::new((*self.expr2).term()),
+            ),
+            _ => Term::Unsupported(self.to_string()),
+        }
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        (*self.expr1).type_check(errors);
+        (*self.expr2).type_check(errors);
+        let (p1, p2) = ((*self.expr1).get_type(), (*self.expr2).get_type());
+        if *self.get_type() == type_invalid() && valid(&[p1, p2]) {
+            let tag = self.op_base.expr_base.op.get_tag().unwrap();
+            errors.push((
+                self.line,
+                format!(
+                    "cannot {} {} and {}",
+                    Arith::verb(tag),
+                    p1.word.lexeme,
+                    p2.word.lexeme
+                ),
+            ));
+        }
+    }
+
     // Explicitly inherited:
 
     fn reduce(&self) -> Box<dyn ExprAble> {
@@ -301,361 +597,1901 @@ impl ExprAble for Arith {
     get_type! {self, op_base}
 }
 
-pub struct Unary {
+/// Arithmetic that is only defined on integers: %, &, |, ^, << and >>
+pub struct IntArith {
     op_base: OpBase,
-    expr: Box<dyn ExprAble>,
+    expr1: Box<dyn ExprAble>,
+    expr2: Box<dyn ExprAble>,
+    line: u32,
+    temp_count: Rc<RefCell<u32>>,
 }
 
-impl Unary {
-    pub fn new(tok: Token, x: Box<dyn ExprAble>, count: Rc<RefCell<u8>>) -> Unary {
-        let type_ = TypeBase::max(&type_int(), (*x).get_type());
-        if type_ == None {
-            panic!("type error");
+impl IntArith {
+    pub fn new(
+        tok: Token,
+        x1: Box<dyn ExprAble>,
+        x2: Box<dyn ExprAble>,
+        line: u32,
+        count: Rc<RefCell<u32>>,
+    ) -> IntArith {
+        let (x1, x2) = adopt_pair(x1, x2);
+        let type1 = (*x1).get_type();
+        let type2 = (*x2).get_type();
+        if !integral(type1) || !integral(type2) {
+            return IntArith {
+                op_base: OpBase::new(tok, type_invalid(), count.clone()),
+                expr1: x1,
+                expr2: x2,
+                line,
+                temp_count: count,
+            };
         }
 
-        Unary {
-            op_base: OpBase::new(tok, type_.unwrap(), count),
-            expr: x,
+        // A shift has the type of its left operand, the right one is only a count
+        let tag = tok.get_tag().unwrap();
+        if tag == Tag::Shl as u32 || tag == Tag::Shr as u32 {
+            return IntArith {
+                op_base: OpBase::new(tok, type1.clone(), count.clone()),
+                expr1: x1,
+                expr2: x2,
+                line,
+                temp_count: count,
+            };
+        }
+
+        let p = TypeBase::max(type1, type2).unwrap();
+        IntArith {
+            expr1: widen(x1, &p, count.clone()),
+            expr2: widen(x2, &p, count.clone()),
+            op_base: OpBase::new(tok, p, count.clone()),
+            line,
+            temp_count: count,
         }
     }
 }
 
-impl ExprAble for Unary {
+impl ExprAble for IntArith {
     fn gen(&self) -> Box<dyn ExprAble> {
-        Box::new(Unary::new(
-            self.op_base.expr_base.op.clone(),
-            (*self.expr).reduce(),
-            self.op_base.temp_count.clone(),
-        ))
+        Box::new(IntArith {
+            op_base: OpBase::new(
+                self.op_base.expr_base.op.clone(),
+                self.get_type().clone(),
+                self.op_base.temp_count.clone(),
+            ),
+            expr1: self.expr1.reduce(),
+            expr2: self.expr2.reduce(),
+            line: self.line,
+            temp_count: self.temp_count.clone(),
+        })
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        op_reduce!(self)
     }
 
     fn to_string(&self) -> String {
-        self.op_base.expr_base.op.to_string().clone() + &(*self.expr).to_string()
+        format!(
+            "{} {} {}",
+            (*self.expr1).to_string(),
+            self.op_base.expr_base.op.to_string(),
+            (*self.expr2).to_string()
+        )
     }
 
-    // Explicitly inherited
-    reduce! {self, op_base}
+    fn type_check(&self, errors: &mut TypeErrors) {
+        (*self.expr1).type_check(errors);
+        (*self.expr2).type_check(errors);
+        let (p1, p2) = ((*self.expr1).get_type(), (*self.expr2).get_type());
+        if *self.get_type() == type_invalid() && valid(&[p1, p2]) {
+            errors.push((
+                self.line,
+                format!(
+                    "operator {} requires integer operands, found {} and {}",
+                    self.op_base.expr_base.op.to_string(),
+                    p1.word.lexeme,
+                    p2.word.lexeme
+                ),
+            ));
+        }
+    }
+
+    // Explicitly inherited:
+
     jumping! {self, op_base}
     emit_jumps! {self, op_base}
     get_type! {self, op_base}
 }
 
-pub struct Constant {
-    expr_base: ExprBase,
+pub struct Unary {
+    op_base: OpBase,
+    expr: Box<dyn ExprAble>,
+    line: u32,
+    temp_count: Rc<RefCell<u32>>,
 }
 
-impl Constant {
-    pub fn new(tok: Token, p: TypeBase) -> Constant {
-        Constant {
-            expr_base: ExprBase::new(tok, p),
+impl Unary {
+    /// -x where x is promoted to int first if it is narrower
+    pub fn new(tok: Token, x: Box<dyn ExprAble>, line: u32, count: Rc<RefCell<u32>>) -> Unary {
+        let type_ = match TypeBase::max(&type_int(), (*x).get_type()) {
+            Some(p) => p,
+            None => {
+                return Unary {
+                    op_base: OpBase::new(tok, type_invalid(), count.clone()),
+                    expr: x,
+                    line,
+                    temp_count: count,
+                }
+            }
+        };
+
+        Unary {
+            expr: widen(x, &type_, count.clone()),
+            op_base: OpBase::new(tok, type_, count.clone()),
+            line,
+            temp_count: count,
         }
     }
 }
 
-#[inline]
-pub fn constant_true() -> Constant {
-    Constant {
-        expr_base: ExprBase::new(Token::Word(Word::Word(word_true())), type_bool()),
+impl ExprAble for Unary {
+    fn gen(&self) -> Box<dyn ExprAble> {
+        if *self.get_type() == type_bigint() {
+            let x = (*self.expr).reduce();
+            return match (*x).big_literal() {
+                Some(v) => big_constant(-&v),
+                None => call("bigint_neg", vec![x], type_bigint()),
+            };
+        }
+
+        Box::new(Unary {
+            op_base: OpBase::new(
+                self.op_base.expr_base.op.clone(),
+                self.get_type().clone(),
+                self.op_base.temp_count.clone(),
+            ),
+            expr: (*self.expr).reduce(),
+            line: self.line,
+            temp_count: self.temp_count.clone(),
+        })
     }
-}
 
-#[inline]
-pub fn constant_false() -> Constant {
-    Constant {
-        expr_base: ExprBase::new(Token::Word(Word::Word(word_false())), type_bool()),
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        op_reduce!(self)
     }
-}
 
-impl ExprAble for Constant {
-    fn jumping(&self, t: u32, f: u32) {
-        match &self.expr_base.op {
-            Token::Word(word) => match word {
-                Word::Word(base) => {
-                    if (base.lexeme == "true".to_string()) && (t != 0) {
-                        emit(format!("goto L{}", t));
-                    } else if (base.lexeme == "false".to_string()) && (f != 0) {
-                        emit(format!("goto L{}", f));
-                    }
-                }
-                _ => {}
-            },
-            _ => {}
+    fn to_string(&self) -> String {
+        self.op_base.expr_base.op.to_string().clone() + &(*self.expr).to_string()
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        (*self.expr).type_check(errors);
+        let p = (*self.expr).get_type();
+        if *self.get_type() == type_invalid() && valid(&[p]) {
+            errors.push((
+                self.line,
+                format!(
+                    "operator - requires a numeric operand, found {}",
+                    p.word.lexeme
+                ),
+            ));
         }
     }
 
-    // Explicitly inherited:
+    fn term(&self) -> Term {
+        if *self.get_type() != type_int() {
+            return Term::Unsupported(self.to_string());
+        }
+        Term::Neg(Box::new((*self.expr).term()))
+    }
 
-    gen! {self, expr_base}
-    reduce! {self, expr_base}
-    emit_jumps! {self, expr_base}
-    to_string! {self, expr_base}
-    get_type! {self, expr_base}
-}
+    fn negated(&self, p: &TypeBase) -> Option<Box<dyn ExprAble>> {
+        let v = (*self.expr).literal()?;
+        let fits = *p == type_bigint()
+            || match p.signed() {
+                Some(true) => v <= p.max_value() + 1,
+                Some(false) => v == 0,
+                None => false,
+            };
+        if !fits {
+            return None;
+        }
+        Some(Box::new(Unary {
+            op_base: OpBase::new(
+                self.op_base.expr_base.op.clone(),
+                p.clone(),
+                self.temp_count.clone(),
+            ),
+            expr: Box::new(Constant::new(Token::Num(Num::new(v)), p.clone())),
+            line: self.line,
+            temp_count: self.temp_count.clone(),
+        }))
+    }
 
-pub fn new_label(labels: Rc<RefCell<u32>>) -> u32 {
-    *labels.borrow_mut() += 1;
-    *labels.borrow()
+    // Explicitly inherited
+    jumping! {self, op_base}
+    emit_jumps! {self, op_base}
+    get_type! {self, op_base}
 }
 
-struct Logical {
-    pub expr_base: ExprBase,
-    pub expr1: Box<dyn ExprAble>,
-    pub expr2: Box<dyn ExprAble>,
-    temp_count: Rc<RefCell<u8>>,
-    labels: Rc<RefCell<u32>>,
+/// Bitwise complement ~x of an integer
+pub struct BitNot {
+    op_base: OpBase,
+    expr: Box<dyn ExprAble>,
+    line: u32,
+    temp_count: Rc<RefCell<u32>>,
 }
 
-macro_rules! logical_construct {
-    ( $check:expr, $tok:ident, $x1:ident, $x2:ident, $count:ident, $labels:ident ) => {{
-        if $check((*$x1).get_type(), (*$x2).get_type()) {
-            Logical {
-                expr_base: ExprBase::new($tok, type_bool()),
-                expr1: $x1,
-                expr2: $x2,
-                temp_count: $count,
-                labels: $labels,
+impl BitNot {
+    pub fn new(tok: Token, x: Box<dyn ExprAble>, line: u32, count: Rc<RefCell<u32>>) -> BitNot {
+        let p = if integral((*x).get_type()) {
+            (*x).get_type().clone()
+        } else {
+            type_invalid()
+        };
+
+        BitNot {
+            op_base: OpBase::new(tok, p, count.clone()),
+            expr: x,
+            line,
+            temp_count: count,
+        }
+    }
+}
+
+impl ExprAble for BitNot {
+    fn gen(&self) -> Box<dyn ExprAble> {
+        Box::new(BitNot {
+            op_base: OpBase::new(
+                self.op_base.expr_base.op.clone(),
+                self.get_type().clone(),
+                self.op_base.temp_count.clone(),
+            ),
+            expr: (*self.expr).reduce(),
+            line: self.line,
+            temp_count: self.temp_count.clone(),
+        })
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        op_reduce!(self)
+    }
+
+    fn to_string(&self) -> String {
+        self.op_base.expr_base.op.to_string() + &(*self.expr).to_string()
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        (*self.expr).type_check(errors);
+        let p = (*self.expr).get_type();
+        if *self.get_type() == type_invalid() && valid(&[p]) {
+            errors.push((
+                self.line,
+                format!(
+                    "operator ~ requires an integer operand, found {}",
+                    p.word.lexeme
+                ),
+            ));
+        }
+    }
+
+    // Explicitly inherited:
+
+    jumping! {self, op_base}
+    emit_jumps! {self, op_base}
+    get_type! {self, op_base}
+}
+
+pub struct Cast {
+    op_base: OpBase,
+    expr: Box<dyn ExprAble>,
+    to: TypeBase, // target type, the type of the node is invalid if x cannot be cast to it
+    line: u32,
+    temp_count: Rc<RefCell<u32>>,
+}
+
+impl Cast {
+    pub fn new(p: TypeBase, x: Box<dyn ExprAble>, line: u32, count: Rc<RefCell<u32>>) -> Cast {
+        let type_ = if Cast::check(&p, (*x).get_type()) {
+            p.clone()
+        } else {
+            type_invalid()
+        };
+
+        Cast {
+            op_base: OpBase::new(Token::Word(Word::Type(p.clone())), type_, count.clone()),
+            expr: x,
+            to: p,
+            line,
+            temp_count: count,
+        }
+    }
+
+    fn check(to: &TypeBase, from: &TypeBase) -> bool {
+        if (*to == *from && !to.is_aggregate()) || (numeric(to) && numeric(from)) {
+            true
+        } else if to.pointee().is_some() {
+            from.pointee().is_some() || integral(from)
+        } else {
+            integral(to) && (from.pointee().is_some() || from.is_enum())
+        }
+    }
+}
+
+/// Wraps x into a conversion to p unless it already has type p. Only used where the type
+/// checker widens an operand so the conversion is always valid, nothing is converted from or to
+/// the invalid type.
+fn widen(x: Box<dyn ExprAble>, p: &TypeBase, count: Rc<RefCell<u32>>) -> Box<dyn ExprAble> {
+    if *(*x).get_type() == *p || !valid(&[(*x).get_type(), p]) {
+        x
+    } else {
+        Box::new(Cast {
+            op_base: OpBase::new(Token::Word(Word::Type(p.clone())), p.clone(), count.clone()),
+            expr: x,
+            to: p.clone(),
+            line: 0,
+            temp_count: count,
+        })
+    }
+}
+
+impl ExprAble for Cast {
+    fn gen(&self) -> Box<dyn ExprAble> {
+        // Conversions from and to bigint are done by the runtime, bigint_from_<type> and
+        // bigint_to_<type>
+        let to = self.get_type();
+        let from = (*self.expr).get_type();
+        if *to == type_bigint() {
+            let x = (*self.expr).reduce();
+            if let Some(v) = (*x).literal() {
+                return big_constant(BigInt::from_u64(v));
+            }
+            return call(
+                &format!("bigint_from_{}", from.word.lexeme),
+                vec![x],
+                to.clone(),
+            );
+        } else if *from == type_bigint() {
+            let x = (*self.expr).reduce();
+            return call(
+                &format!("bigint_to_{}", to.word.lexeme),
+                vec![x],
+                to.clone(),
+            );
+        }
+
+        Box::new(Cast {
+            op_base: OpBase::new(
+                self.op_base.expr_base.op.clone(),
+                self.get_type().clone(),
+                self.op_base.temp_count.clone(),
+            ),
+            expr: (*self.expr).reduce(),
+            to: self.to.clone(),
+            line: self.line,
+            temp_count: self.temp_count.clone(),
+        })
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        op_reduce!(self)
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "({}) {}",
+            self.op_base.expr_base.op.to_string(),
+            (*self.expr).to_string()
+        )
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        (*self.expr).type_check(errors);
+        let p = (*self.expr).get_type();
+        if *self.get_type() == type_invalid() && valid(&[p, &self.to]) {
+            errors.push((
+                self.line,
+                format!("cannot cast {} to {}", p.word.lexeme, self.to.word.lexeme),
+            ));
+        }
+    }
+
+    // Explicitly inherited:
+
+    jumping! {self, op_base}
+    emit_jumps! {self, op_base}
+    get_type! {self, op_base}
+}
+
+pub struct AddrOf {
+    op_base: OpBase,
+    expr: Box<dyn ExprAble>,
+    line: u32,
+    temp_count: Rc<RefCell<u32>>,
+}
+
+impl AddrOf {
+    pub fn new(tok: Token, x: Box<dyn ExprAble>, line: u32, count: Rc<RefCell<u32>>) -> AddrOf {
+        let p = if (*x).is_variable() {
+            type_pointer((*x).get_type())
+        } else {
+            type_invalid()
+        };
+        AddrOf {
+            op_base: OpBase::new(tok, p, count.clone()),
+            expr: x,
+            line,
+            temp_count: count,
+        }
+    }
+}
+
+impl ExprAble for AddrOf {
+    fn gen(&self) -> Box<dyn ExprAble> {
+        Box::new(AddrOf::new(
+            self.op_base.expr_base.op.clone(),
+            (*self.expr).gen(),
+            self.line,
+            self.op_base.temp_count.clone(),
+        ))
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        op_reduce!(self)
+    }
+
+    fn to_string(&self) -> String {
+        self.op_base.expr_base.op.to_string() + &(*self.expr).to_string()
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        (*self.expr).type_check(errors);
+        if !(*self.expr).is_variable() && valid(&[(*self.expr).get_type()]) {
+            errors.push((self.line, "variable required after &".to_string()));
+        }
+    }
+
+    // Explicitly inherited:
+
+    jumping! {self, op_base}
+    emit_jumps! {self, op_base}
+    get_type! {self, op_base}
+}
+
+pub struct Deref {
+    op_base: OpBase,
+    expr: Box<dyn ExprAble>,
+    line: u32,
+    temp_count: Rc<RefCell<u32>>,
+}
+
+impl Deref {
+    pub fn new(tok: Token, x: Box<dyn ExprAble>, line: u32, count: Rc<RefCell<u32>>) -> Deref {
+        let p = match (*x).get_type().pointee() {
+            Some(of) if !of.is_aggregate() => of.clone(),
+            _ => type_invalid(),
+        };
+        Deref {
+            op_base: OpBase::new(tok, p, count.clone()),
+            expr: x,
+            line,
+            temp_count: count,
+        }
+    }
+}
+
+impl ExprAble for Deref {
+    fn gen(&self) -> Box<dyn ExprAble> {
+        Box::new(Deref {
+            op_base: OpBase::new(
+                self.op_base.expr_base.op.clone(),
+                self.get_type().clone(),
+                self.op_base.temp_count.clone(),
+            ),
+            expr: (*self.expr).reduce(),
+            line: self.line,
+            temp_count: self.temp_count.clone(),
+        })
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        op_reduce!(self)
+    }
+
+    fn jumping(&self, t: u32, f: u32) {
+        self.emit_jumps(self.reduce().to_string(), t, f);
+    }
+
+    fn to_string(&self) -> String {
+        self.op_base.expr_base.op.to_string() + &(*self.expr).to_string()
+    }
+
+    fn is_lvalue(&self) -> bool {
+        true
+    }
+
+    fn store(&self, x: &dyn ExprAble) -> Box<dyn ExprAble> {
+        let p = (*self.expr).reduce();
+        let x = x.reduce();
+        emit(format!("*{} = {}", p.to_string(), x.to_string()));
+        x
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        (*self.expr).type_check(errors);
+        let p = (*self.expr).get_type();
+        if *self.get_type() == type_invalid() && valid(&[p]) {
+            let e = match p.pointee() {
+                Some(of) => format!("cannot dereference a pointer to {}", of.word.lexeme),
+                None => format!("pointer required in dereference, found {}", p.word.lexeme),
+            };
+            errors.push((self.line, e));
+        }
+    }
+
+    // Explicitly inherited:
+
+    emit_jumps! {self, op_base}
+    get_type! {self, op_base}
+}
+
+/// Access x [ i ] of the value at offset i inside the aggregate variable x
+pub struct Access {
+    expr_base: ExprBase,
+    id: Box<dyn ExprAble>,
+    offset: u32,
+    element: Option<(usize, u32)>, // index and line of x.n in the source, see element
+    temp_count: Rc<RefCell<u32>>,
+}
+
+impl Access {
+    pub fn new(i: Box<dyn ExprAble>, offset: u32, p: TypeBase, count: Rc<RefCell<u32>>) -> Access {
+        Access {
+            expr_base: ExprBase::new(Token::Num(Num::new(offset as u64)), p),
+            id: i,
+            offset,
+            element: None,
+            temp_count: count,
+        }
+    }
+
+    /// Element x.n of the tuple variable x
+    pub fn element(i: Box<dyn ExprAble>, n: usize, line: u32, count: Rc<RefCell<u32>>) -> Access {
+        let p = (*i).get_type().clone();
+        let mut x = match p.elements() {
+            Some(elements) if (*i).is_lvalue() && n < elements.len() => {
+                Access::new(i, p.element_offset(n), elements[n].clone(), count)
+            }
+            _ => Access::new(i, 0, type_invalid(), count),
+        };
+        x.element = Some((n, line));
+        x
+    }
+}
+
+impl ExprAble for Access {
+    fn gen(&self) -> Box<dyn ExprAble> {
+        Box::new(Access::new(
+            (*self.id).gen(),
+            self.offset,
+            self.get_type().clone(),
+            self.temp_count.clone(),
+        ))
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        op_reduce!(self)
+    }
+
+    fn jumping(&self, t: u32, f: u32) {
+        self.emit_jumps(self.reduce().to_string(), t, f);
+    }
+
+    fn to_string(&self) -> String {
+        format!("{} [ {} ]", (*self.id).to_string(), self.offset)
+    }
+
+    fn is_lvalue(&self) -> bool {
+        true
+    }
+
+    fn store(&self, x: &dyn ExprAble) -> Box<dyn ExprAble> {
+        let x = x.reduce();
+        emit(format!("{} = {}", self.to_string(), x.to_string()));
+        x
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        (*self.id).type_check(errors);
+        let p = (*self.id).get_type();
+        let n = match self.element {
+            Some((n, line)) if *self.get_type() == type_invalid() && valid(&[p]) => (n, line),
+            _ => return,
+        };
+        let e = if p.elements().is_some() && (*self.id).is_lvalue() {
+            format!("{} has no element {}", p.word.lexeme, n.0)
+        } else {
+            format!("{} is not a tuple variable", (*self.id).to_string())
+        };
+        errors.push((n.1, e));
+    }
+
+    // Explicitly inherited:
+
+    emit_jumps! {self, expr_base}
+    get_type! {self, expr_base}
+}
+
+pub struct Constant {
+    expr_base: ExprBase,
+}
+
+impl Constant {
+    pub fn new(tok: Token, p: TypeBase) -> Constant {
+        Constant {
+            expr_base: ExprBase::new(tok, p),
+        }
+    }
+}
+
+#[inline]
+pub fn constant_true() -> Constant {
+    Constant {
+        expr_base: ExprBase::new(Token::Word(Word::Word(word_true())), type_bool()),
+    }
+}
+
+#[inline]
+pub fn constant_false() -> Constant {
+    Constant {
+        expr_base: ExprBase::new(Token::Word(Word::Word(word_false())), type_bool()),
+    }
+}
+
+/// An integer literal takes the type of the other operand when its value fits in it, so that
+/// b + 1 stays a u8 when b is one and c = 65 stores a char. A negated literal may be as low as
+/// the minimum of a signed type, so x = -1 stores an i8.
+fn adopt(x: Box<dyn ExprAble>, p: &TypeBase) -> Box<dyn ExprAble> {
+    if let Some(y) = (*x).negated(p) {
+        return y;
+    }
+    match (*x).literal() {
+        Some(v)
+            if *p == type_bigint()
+                || ((integral(p) || *p == type_char()) && v <= p.max_value()) =>
+        {
+            Box::new(Constant::new(Token::Num(Num::new(v)), p.clone()))
+        }
+        _ => x,
+    }
+}
+
+fn adopt_pair(
+    x1: Box<dyn ExprAble>,
+    x2: Box<dyn ExprAble>,
+) -> (Box<dyn ExprAble>, Box<dyn ExprAble>) {
+    let x1 = adopt(x1, (*x2).get_type());
+    let x2 = adopt(x2, (*x1).get_type());
+    (x1, x2)
+}
+
+impl ExprAble for Constant {
+    fn literal(&self) -> Option<u64> {
+        match &self.expr_base.op {
+            Token::Num(n) if integral(&self.expr_base.type_) => Some(n.value),
+            _ => None,
+        }
+    }
+
+    fn is_constant(&self) -> bool {
+        true
+    }
+
+    fn term(&self) -> Term {
+        match &self.expr_base.op {
+            Token::Num(n) if self.expr_base.type_ == type_int() => Term::Num(n.value),
+            Token::Word(Word::Word(w)) if w.lexeme == "true" => Term::Bool(true),
+            Token::Word(Word::Word(w)) if w.lexeme == "false" => Term::Bool(false),
+            _ => Term::Unsupported(self.to_string()),
+        }
+    }
+
+    fn big_literal(&self) -> Option<BigInt> {
+        if self.expr_base.type_ != type_bigint() {
+            return None;
+        }
+        match &self.expr_base.op {
+            Token::Num(n) => Some(BigInt::from_u64(n.value)),
+            Token::BigNum(big) => BigInt::parse(&big.digits),
+            _ => None,
+        }
+    }
+
+    fn jumping(&self, t: u32, f: u32) {
+        match &self.expr_base.op {
+            Token::Word(word) => match word {
+                Word::Word(base) => {
+                    if (base.lexeme == "true".to_string()) && (t != 0) {
+                        emit(format!("goto L{}", t));
+                    } else if (base.lexeme == "false".to_string()) && (f != 0) {
+                        emit(format!("goto L{}", f));
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    // A constant stays one so that literals can still be recognized after reduction
+    fn gen(&self) -> Box<dyn ExprAble> {
+        Box::new(Constant {
+            expr_base: self.expr_base.clone(),
+        })
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        self.gen()
+    }
+
+    // Explicitly inherited:
+
+    emit_jumps! {self, expr_base}
+    to_string! {self, expr_base}
+    get_type! {self, expr_base}
+}
+
+pub fn new_label(labels: Rc<RefCell<u32>>) -> u32 {
+    *labels.borrow_mut() += 1;
+    *labels.borrow()
+}
+
+struct Logical {
+    pub expr_base: ExprBase,
+    pub expr1: Box<dyn ExprAble>,
+    pub expr2: Box<dyn ExprAble>,
+    line: u32,
+    temp_count: Rc<RefCell<u32>>,
+    labels: Rc<RefCell<u32>>,
+}
+
+macro_rules! logical_construct {
+    ( $check:expr, $tok:ident, $x1:ident, $x2:ident, $line:ident, $count:ident, $labels:ident ) => {{
+        let p = if $check((*$x1).get_type(), (*$x2).get_type()) {
+            type_bool()
+        } else {
+            type_invalid()
+        };
+        Logical {
+            expr_base: ExprBase::new($tok, p),
+            expr1: $x1,
+            expr2: $x2,
+            line: $line,
+            temp_count: $count,
+            labels: $labels,
+        }
+    }};
+}
+
+impl Logical {
+    fn new(
+        tok: Token,
+        x1: Box<dyn ExprAble>,
+        x2: Box<dyn ExprAble>,
+        line: u32,
+        count: Rc<RefCell<u32>>,
+        labels: Rc<RefCell<u32>>,
+    ) -> Logical {
+        logical_construct!(Logical::check, tok, x1, x2, line, count, labels)
+    }
+
+    // Checks the operands and reports the error of the operator op
+    fn check_operands(&self, errors: &mut TypeErrors, e: impl Fn(&TypeBase, &TypeBase) -> String) {
+        (*self.expr1).type_check(errors);
+        (*self.expr2).type_check(errors);
+        let (p1, p2) = ((*self.expr1).get_type(), (*self.expr2).get_type());
+        if *self.get_type() == type_invalid() && valid(&[p1, p2]) {
+            errors.push((self.line, e(p1, p2)));
+        }
+    }
+
+    fn check(p1: &TypeBase, p2: &TypeBase) -> bool {
+        if *p1 == type_bool() && *p2 == type_bool() {
+            true
+        } else {
+            false
+        }
+    }
+}
+
+macro_rules! logical_gen {
+    ( $self:expr, $labels:expr, $count:expr ) => {{
+        let f = new_label($labels.clone());
+        let a = new_label($labels.clone());
+        let temp = Temp::new((*$self.get_type()).clone(), $count.clone());
+        $self.jumping(0, f);
+        emit(format!("{} = true", temp.to_string()));
+        emit(format!("goto L{}", a));
+        emit_label(f);
+        emit(format!("{} = false", temp.to_string()));
+        emit_label(a);
+        Box::new(temp)
+    }};
+}
+
+impl ExprAble for Logical {
+    fn gen(&self) -> Box<dyn ExprAble> {
+        logical_gen!(self, self.labels, self.temp_count)
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "{} {} {}",
+            (*self.expr1).to_string(),
+            self.expr_base.op.to_string(),
+            (*self.expr2).to_string()
+        )
+    }
+
+    // Explicitly inherited:
+
+    reduce! {self, expr_base}
+    jumping! {self, expr_base}
+    emit_jumps! {self, expr_base}
+    get_type! {self, expr_base}
+}
+
+pub struct And {
+    logic: Logical,
+}
+
+impl And {
+    pub fn new(
+        tok: Token,
+        x1: Box<dyn ExprAble>,
+        x2: Box<dyn ExprAble>,
+        line: u32,
+        count: Rc<RefCell<u32>>,
+        labels: Rc<RefCell<u32>>,
+    ) -> And {
+        And {
+            logic: Logical::new(tok, x1, x2, line, count, labels),
+        }
+    }
+}
+
+impl ExprAble for And {
+    fn jumping(&self, t: u32, f: u32) {
+        let label: u32;
+        if f != 0 {
+            label = f;
+        } else {
+            label = new_label(self.logic.labels.clone());
+        }
+        self.logic.expr1.jumping(0, label);
+        self.logic.expr2.jumping(t, f);
+        if f == 0 {
+            emit_label(label);
+        }
+    }
+
+    fn term(&self) -> Term {
+        Term::And(
+            Box::new((*self.logic.expr1).term()),
+            Box::new((*self.logic.expr2).term()),
+        )
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        self.logic.check_operands(errors, |p1, p2| {
+            format!(
+                "operator && requires bool operands, found {} and {}",
+                p1.word.lexeme, p2.word.lexeme
+            )
+        });
+    }
+
+    fn gen(&self) -> Box<dyn ExprAble> {
+        logical_gen!(self, self.logic.labels, self.logic.temp_count)
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        self.gen()
+    }
+
+    // Explicitly inherited:
+
+    emit_jumps! {self, logic}
+    to_string! {self, logic}
+    get_type! {self, logic}
+}
+
+pub struct Or {
+    logic: Logical,
+}
+
+impl Or {
+    pub fn new(
+        tok: Token,
+        x1: Box<dyn ExprAble>,
+        x2: Box<dyn ExprAble>,
+        line: u32,
+        count: Rc<RefCell<u32>>,
+        labels: Rc<RefCell<u32>>,
+    ) -> Or {
+        Or {
+            logic: Logical::new(tok, x1, x2, line, count, labels),
+        }
+    }
+}
+
+impl ExprAble for Or {
+    fn jumping(&self, t: u32, f: u32) {
+        let label: u32;
+        if t != 0 {
+            label = t;
+        } else {
+            label = new_label(self.logic.labels.clone());
+        }
+        self.logic.expr1.jumping(label, 0);
+        self.logic.expr2.jumping(t, f);
+        if t == 0 {
+            emit_label(label);
+        }
+    }
+
+    fn term(&self) -> Term {
+        Term::Or(
+            Box::new((*self.logic.expr1).term()),
+            Box::new((*self.logic.expr2).term()),
+        )
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        self.logic.check_operands(errors, |p1, p2| {
+            format!(
+                "operator || requires bool operands, found {} and {}",
+                p1.word.lexeme, p2.word.lexeme
+            )
+        });
+    }
+
+    fn gen(&self) -> Box<dyn ExprAble> {
+        logical_gen!(self, self.logic.labels, self.logic.temp_count)
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        self.gen()
+    }
+
+    // Explicitly inherited:
+
+    emit_jumps! {self, logic}
+    to_string! {self, logic}
+    get_type! {self, logic}
+}
+
+pub struct Not {
+    logic: Logical,
+}
+
+impl Not {
+    pub fn new(
+        tok: Token,
+        x2: Box<dyn ExprAble>,
+        line: u32,
+        count: Rc<RefCell<u32>>,
+        labels: Rc<RefCell<u32>>,
+    ) -> Not {
+        // I use the constant true as an unuseful thing cause Logical requires 2 pointers
+        // TODO: rewrite it
+
+        Not {
+            logic: Logical::new(tok, Box::new(constant_true()), x2, line, count, labels),
+        }
+    }
+}
+
+impl ExprAble for Not {
+    fn jumping(&self, t: u32, f: u32) {
+        (*self.logic.expr2).jumping(f, t);
+    }
+
+    fn term(&self) -> Term {
+        Term::Not(Box::new((*self.logic.expr2).term()))
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        self.logic.check_operands(errors, |_, p| {
+            format!(
+                "operator ! requires a bool operand, found {}",
+                p.word.lexeme
+            )
+        });
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "{} {}",
+            self.logic.expr_base.op.to_string(),
+            self.logic.expr2.to_string()
+        )
+    }
+
+    fn gen(&self) -> Box<dyn ExprAble> {
+        logical_gen!(self, self.logic.labels, self.logic.temp_count)
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        self.gen()
+    }
+
+    // Explicitly inherited:
+    emit_jumps! {self, logic}
+    get_type! {self, logic}
+}
+
+pub struct Cond {
+    expr_base: ExprBase,
+    cond: Box<dyn ExprAble>,
+    expr1: Box<dyn ExprAble>,
+    expr2: Box<dyn ExprAble>,
+    line: u32,
+    temp_count: Rc<RefCell<u32>>,
+    labels: Rc<RefCell<u32>>,
+}
+
+impl Cond {
+    pub fn new(
+        tok: Token,
+        c: Box<dyn ExprAble>,
+        x1: Box<dyn ExprAble>,
+        x2: Box<dyn ExprAble>,
+        line: u32,
+        count: Rc<RefCell<u32>>,
+        labels: Rc<RefCell<u32>>,
+    ) -> Cond {
+        let (x1, x2) = adopt_pair(x1, x2);
+        let type1 = (*x1).get_type();
+        let type2 = (*x2).get_type();
+        let p = match TypeBase::max(type1, type2) {
+            Some(p) => p,
+            None if *type1 == *type2 && !type1.is_aggregate() => type1.clone(),
+            None => {
+                return Cond {
+                    cond: c,
+                    expr1: x1,
+                    expr2: x2,
+                    expr_base: ExprBase::new(tok, type_invalid()),
+                    line,
+                    temp_count: count,
+                    labels,
+                }
+            }
+        };
+
+        Cond {
+            cond: c,
+            expr1: widen(x1, &p, count.clone()),
+            expr2: widen(x2, &p, count.clone()),
+            expr_base: ExprBase::new(tok, p),
+            line,
+            temp_count: count,
+            labels,
+        }
+    }
+}
+
+impl ExprAble for Cond {
+    fn gen(&self) -> Box<dyn ExprAble> {
+        let f = new_label(self.labels.clone());
+        let a = new_label(self.labels.clone());
+        let temp = Temp::new((*self.get_type()).clone(), self.temp_count.clone());
+        self.cond.jumping(0, f);
+        emit(format!(
+            "{} = {}",
+            temp.to_string(),
+            self.expr1.gen().to_string()
+        ));
+        emit(format!("goto L{}", a));
+        emit_label(f);
+        emit(format!(
+            "{} = {}",
+            temp.to_string(),
+            self.expr2.gen().to_string()
+        ));
+        emit_label(a);
+        Box::new(temp)
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        self.gen()
+    }
+
+    fn jumping(&self, t: u32, f: u32) {
+        self.emit_jumps(self.reduce().to_string(), t, f);
+    }
+
+    fn term(&self) -> Term {
+        Term::Cond(
+            Box::new((*self.cond).term()),
+            Box::new((*self.expr1).term()),
+            Box::new((*self.expr2).term()),
+        )
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        bool_check(&*self.cond, self.line, "conditional expression", errors);
+        (*self.expr1).type_check(errors);
+        (*self.expr2).type_check(errors);
+        let (p1, p2) = ((*self.expr1).get_type(), (*self.expr2).get_type());
+        if *self.get_type() == type_invalid() && valid(&[p1, p2]) {
+            errors.push((
+                self.line,
+                format!(
+                    "branches of conditional expression have different types {} and {}",
+                    p1.word.lexeme, p2.word.lexeme
+                ),
+            ));
+        }
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "{} ? {} : {}",
+            (*self.cond).to_string(),
+            (*self.expr1).to_string(),
+            (*self.expr2).to_string()
+        )
+    }
+
+    // Explicitly inherited:
+
+    emit_jumps! {self, expr_base}
+    get_type! {self, expr_base}
+}
+
+pub struct Rel {
+    logic: Logical,
+}
+
+impl Rel {
+    pub fn new(
+        tok: Token,
+        x1: Box<dyn ExprAble>,
+        x2: Box<dyn ExprAble>,
+        line: u32,
+        count: Rc<RefCell<u32>>,
+        labels: Rc<RefCell<u32>>,
+    ) -> Rel {
+        // Numbers of different types are compared in the type both convert to
+        let (x1, x2) = adopt_pair(x1, x2);
+        let type1 = (*x1).get_type();
+        let type2 = (*x2).get_type();
+        let (x1, x2) = if numeric(type1) && numeric(type2) && *type1 != *type2 {
+            if integral(type1) && integral(type2) && type1.signed() != type2.signed() {
+                warning(
+                    &format!(
+                        "comparison of {} and {} mixes signed and unsigned integers",
+                        type1.word.lexeme, type2.word.lexeme
+                    ),
+                    line,
+                );
+            }
+            match TypeBase::max(type1, type2) {
+                Some(p) => (widen(x1, &p, count.clone()), widen(x2, &p, count.clone())),
+                None => (x1, x2),
             }
         } else {
-            panic!("type error");
+            (x1, x2)
+        };
+
+        Rel {
+            logic: logical_construct!(Rel::check, tok, x1, x2, line, count, labels),
         }
-    }};
+    }
+
+    fn check(p1: &TypeBase, p2: &TypeBase) -> bool {
+        *p1 == *p2 && !p1.is_aggregate()
+    }
+}
+
+impl ExprAble for Rel {
+    fn gen(&self) -> Box<dyn ExprAble> {
+        logical_gen!(self, self.logic.labels, self.logic.temp_count)
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        self.gen()
+    }
+
+    fn jumping(&self, t: u32, f: u32) {
+        let a = self.logic.expr1.reduce();
+        let b = self.logic.expr2.reduce();
+        if *(*a).get_type() == type_bigint() {
+            // The runtime compares bigints, the result is negative, zero or positive
+            let c = Temp::new(type_int(), self.logic.temp_count.clone());
+            let x = call("bigint_cmp", vec![a, b], type_int());
+            emit(format!("{} = {}", c.to_string(), (*x).to_string()));
+            let test = format!(
+                "{} {} 0",
+                c.to_string(),
+                (*self.logic.expr_base.get_op()).to_string()
+            );
+            self.emit_jumps(test, t, f);
+            return;
+        }
+        let test = a.to_string()
+            + " "
+            + &(*self.logic.expr_base.get_op()).to_string()
+            + " "
+            + &b.to_string();
+        self.emit_jumps(test, t, f);
+    }
+
+    fn term(&self) -> Term {
+        Term::Rel(
+            self.logic.expr_base.op.get_tag().unwrap(),
+            Box::new((*self.logic.expr1).term()),
+            Box::new((*self.logic.expr2).term()),
+        )
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        self.logic.check_operands(errors, |p1, p2| {
+            format!("cannot compare {} and {}", p1.word.lexeme, p2.word.lexeme)
+        });
+    }
+
+    // Explicitly inherited:
+
+    emit_jumps! {self, logic}
+    to_string! {self, logic}
+    get_type! {self, logic}
 }
 
-impl Logical {
-    fn new(
+pub struct Set {
+    expr_base: ExprBase,
+    id: Box<dyn ExprAble>,
+    expr: Box<dyn ExprAble>,
+    line: u32,
+}
+
+impl Set {
+    pub fn new(
         tok: Token,
-        x1: Box<dyn ExprAble>,
-        x2: Box<dyn ExprAble>,
-        count: Rc<RefCell<u8>>,
-        labels: Rc<RefCell<u32>>,
-    ) -> Logical {
-        logical_construct!(Logical::check, tok, x1, x2, count, labels)
+        i: Box<dyn ExprAble>,
+        x: Box<dyn ExprAble>,
+        line: u32,
+        count: Rc<RefCell<u32>>,
+    ) -> Set {
+        let p = (*i).get_type().clone();
+        let (type_, x) = match Set::convert(&p, x, count) {
+            Ok(x) if (*i).is_lvalue() => (p, x),
+            Ok(x) | Err(x) => (type_invalid(), x),
+        };
+
+        Set {
+            expr_base: ExprBase::new(tok, type_),
+            id: i,
+            expr: x,
+            line,
+        }
     }
 
-    fn check(p1: &TypeBase, p2: &TypeBase) -> bool {
-        if *p1 == type_bool() && *p2 == type_bool() {
-            true
+    // Converts the right side to the type p of the left one, or returns it as it is if it
+    // cannot be assigned
+    fn convert(
+        p: &TypeBase,
+        x: Box<dyn ExprAble>,
+        count: Rc<RefCell<u32>>,
+    ) -> Result<Box<dyn ExprAble>, Box<dyn ExprAble>> {
+        let x = adopt(x, p);
+        if Set::mismatch(p, (*x).get_type()).is_some() {
+            return Err(x);
+        }
+        Ok(widen(x, p, count))
+    }
+
+    // Why a value of type from cannot be assigned to a location of type p, narrowing may lose
+    // data so it requires an explicit cast
+    fn mismatch(p: &TypeBase, from: &TypeBase) -> Option<String> {
+        if !valid(&[p, from]) {
+            None
+        } else if !Set::check(p, from) {
+            Some(format!(
+                "cannot assign {} to {}",
+                from.word.lexeme, p.word.lexeme
+            ))
+        } else if *p != *from && numeric(p) && TypeBase::max(p, from).unwrap() != *p {
+            Some(format!(
+                "implicit conversion from {} to {} loses precision, use an explicit cast",
+                from.word.lexeme, p.word.lexeme
+            ))
         } else {
-            false
+            None
         }
     }
+
+    // Union values are only assigned from a variant constructor, see SetVariant, and tuples
+    // element by element, see MultiSet
+    fn check(p1: &TypeBase, p2: &TypeBase) -> bool {
+        (numeric(p1) && numeric(p2)) || (*p1 == *p2 && !p1.is_aggregate())
+    }
 }
 
-macro_rules! logical_gen {
-    ( $self:expr, $labels:expr, $count:expr ) => {{
-        let f = new_label($labels.clone());
-        let a = new_label($labels.clone());
-        let temp = Temp::new((*$self.get_type()).clone(), $count.clone());
-        $self.jumping(0, f);
-        emit(format!("{} = true", temp.to_string()));
-        emit(format!("goto L{}", a));
-        emit_label(f);
-        emit(format!("{} = false", temp.to_string()));
-        emit_label(a);
-        Box::new(temp)
-    }};
+// Why the left side of an assignment, ++ or -- cannot be stored into
+fn not_assignable(x: &dyn ExprAble) -> Option<String> {
+    if x.is_lvalue() || !valid(&[x.get_type()]) {
+        None
+    } else {
+        Some(format!("{} is not assignable", x.to_string()))
+    }
 }
 
-impl ExprAble for Logical {
+impl ExprAble for Set {
+    // The right side is evaluated once, the value of the assignment is what store returns
     fn gen(&self) -> Box<dyn ExprAble> {
-        logical_gen!(self, self.labels, self.temp_count)
+        (*self.id).store(&*self.expr)
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        self.gen()
+    }
+
+    fn jumping(&self, t: u32, f: u32) {
+        self.emit_jumps(self.reduce().to_string(), t, f);
     }
 
     fn to_string(&self) -> String {
-        format!(
-            "{} {} {}",
-            (*self.expr1).to_string(),
-            self.expr_base.op.to_string(),
-            (*self.expr2).to_string()
-        )
+        format!("{} = {}", (*self.id).to_string(), (*self.expr).to_string())
+    }
+
+    fn assignments(&self) -> Option<Vec<(Term, Term)>> {
+        Some(vec![((*self.id).term(), (*self.expr).term())])
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        (*self.id).type_check(errors);
+        (*self.expr).type_check(errors);
+        if let Some(e) = not_assignable(&*self.id) {
+            errors.push((self.line, e));
+        } else if let Some(e) = Set::mismatch((*self.id).get_type(), (*self.expr).get_type()) {
+            errors.push((self.line, e));
+        }
     }
 
     // Explicitly inherited:
 
-    reduce! {self, expr_base}
-    jumping! {self, expr_base}
     emit_jumps! {self, expr_base}
     get_type! {self, expr_base}
 }
 
-pub struct And {
-    logic: Logical,
+/// Tuple literal (x, y, ...), it can only be assigned
+pub struct Tuple {
+    expr_base: ExprBase,
+    elems: Vec<Box<dyn ExprAble>>,
+    line: u32,
 }
 
-impl And {
+impl Tuple {
+    pub fn new(tok: Token, xs: Vec<Box<dyn ExprAble>>, line: u32) -> Tuple {
+        let types: Vec<TypeBase> = xs.iter().map(|x| (*x).get_type().clone()).collect();
+        let type_ = if types.iter().all(|p| valid(&[p]) && !p.is_aggregate()) {
+            type_tuple(types)
+        } else {
+            type_invalid()
+        };
+        Tuple {
+            expr_base: ExprBase::new(tok, type_),
+            elems: xs,
+            line,
+        }
+    }
+}
+
+impl ExprAble for Tuple {
+    fn gen(&self) -> Box<dyn ExprAble> {
+        error("a tuple literal can only be assigned", self.line);
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        self.gen()
+    }
+
+    fn jumping(&self, _t: u32, _f: u32) {
+        self.gen();
+    }
+
+    fn to_string(&self) -> String {
+        let xs: Vec<String> = self.elems.iter().map(|x| (*x).to_string()).collect();
+        format!("({})", xs.join(", "))
+    }
+
+    fn elements(&mut self) -> Option<Vec<Box<dyn ExprAble>>> {
+        Some(std::mem::take(&mut self.elems))
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        for x in self.elems.iter() {
+            (*x).type_check(errors);
+        }
+        if let Some(x) = self.elems.iter().find(|x| (*x).get_type().is_aggregate()) {
+            errors.push((
+                self.line,
+                format!(
+                    "{} cannot be an element of a tuple",
+                    (*x).get_type().word.lexeme
+                ),
+            ));
+        }
+    }
+
+    // Explicitly inherited:
+
+    emit_jumps! {self, expr_base}
+    get_type! {self, expr_base}
+}
+
+/// a, b, ... = x, y, ... assigns several locations at once. All values are computed before the
+/// first one is stored, so a, b = b, a swaps a and b.
+pub struct MultiSet {
+    expr_base: ExprBase,
+    targets: Vec<Box<dyn ExprAble>>,
+    values: Vec<Box<dyn ExprAble>>,
+    line: u32,
+    temp_count: Rc<RefCell<u32>>,
+}
+
+impl MultiSet {
     pub fn new(
         tok: Token,
-        x1: Box<dyn ExprAble>,
-        x2: Box<dyn ExprAble>,
-        count: Rc<RefCell<u8>>,
-        labels: Rc<RefCell<u32>>,
-    ) -> And {
-        And {
-            logic: Logical::new(tok, x1, x2, count, labels),
+        targets: Vec<Box<dyn ExprAble>>,
+        values: Vec<Box<dyn ExprAble>>,
+        line: u32,
+        count: Rc<RefCell<u32>>,
+    ) -> MultiSet {
+        // Values without a location are kept as they are for the type checker to report
+        let mut converted = Vec::new();
+        for (n, x) in values.into_iter().enumerate() {
+            converted.push(match targets.get(n) {
+                Some(i) => Set::convert((*i).get_type(), x, count.clone()).unwrap_or_else(|x| x),
+                None => x,
+            });
+        }
+
+        let types: Vec<TypeBase> = targets.iter().map(|i| (*i).get_type().clone()).collect();
+        let type_ = if types.iter().all(|p| valid(&[p])) && targets.len() == converted.len() {
+            type_tuple(types)
+        } else {
+            type_invalid()
+        };
+        MultiSet {
+            expr_base: ExprBase::new(tok, type_),
+            targets,
+            values: converted,
+            line,
+            temp_count: count,
         }
     }
 }
 
-impl ExprAble for And {
-    fn jumping(&self, t: u32, f: u32) {
-        let label: u32;
-        if f != 0 {
-            label = f;
-        } else {
-            label = new_label(self.logic.labels.clone());
+impl ExprAble for MultiSet {
+    fn gen(&self) -> Box<dyn ExprAble> {
+        let mut values: Vec<Box<dyn ExprAble>> = Vec::new();
+        for x in self.values.iter() {
+            let x = (*x).gen();
+            if (*x).is_constant() {
+                values.push(x);
+                continue;
+            }
+            let t = Temp::new((*x).get_type().clone(), self.temp_count.clone());
+            emit(format!("{} = {}", t.to_string(), (*x).to_string()));
+            values.push(Box::new(t));
         }
-        self.logic.expr1.jumping(0, label);
-        self.logic.expr2.jumping(t, f);
-        if f == 0 {
-            emit_label(label);
+
+        for (i, x) in self.targets.iter().zip(values) {
+            (*i).gen().store(&*x);
+        }
+        Box::new(self.expr_base.clone())
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        self.gen()
+    }
+
+    fn to_string(&self) -> String {
+        let targets: Vec<String> = self.targets.iter().map(|x| (*x).to_string()).collect();
+        let values: Vec<String> = self.values.iter().map(|x| (*x).to_string()).collect();
+        format!("{} = {}", targets.join(", "), values.join(", "))
+    }
+
+    fn assignments(&self) -> Option<Vec<(Term, Term)>> {
+        Some(
+            self.targets
+                .iter()
+                .zip(self.values.iter())
+                .map(|(i, x)| ((*i).term(), (*x).term()))
+                .collect(),
+        )
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        for x in self.targets.iter().chain(self.values.iter()) {
+            (*x).type_check(errors);
+        }
+        // A tuple of the invalid type stands for one value, so the count is not checked then
+        let types = self.targets.iter().chain(self.values.iter());
+        if self.targets.len() != self.values.len() {
+            if types.map(|x| (*x).get_type()).all(|p| valid(&[p])) {
+                errors.push((
+                    self.line,
+                    format!(
+                        "{} values assigned to {} locations",
+                        self.values.len(),
+                        self.targets.len()
+                    ),
+                ));
+            }
+            return;
+        }
+        for (i, x) in self.targets.iter().zip(self.values.iter()) {
+            if !(*i).is_lvalue() && valid(&[(*i).get_type()]) {
+                errors.push((self.line, format!("cannot assign to {}", (*i).to_string())));
+            } else if let Some(e) = Set::mismatch((*i).get_type(), (*x).get_type()) {
+                errors.push((self.line, e));
+            }
         }
     }
 
     // Explicitly inherited:
 
-    gen! {self, logic}
-    reduce! {self, logic}
-    emit_jumps! {self, logic}
-    to_string! {self, logic}
-    get_type! {self, logic}
+    jumping! {self, expr_base}
+    emit_jumps! {self, expr_base}
+    get_type! {self, expr_base}
 }
 
-pub struct Or {
-    logic: Logical,
+/// u = V(x, ...) stores the tag of the variant V and its data into the union variable u
+pub struct SetVariant {
+    expr_base: ExprBase,
+    id: Box<dyn ExprAble>,
+    tag: u32,
+    fields: Vec<Box<dyn ExprAble>>,
+    line: u32,
+    temp_count: Rc<RefCell<u32>>,
 }
 
-impl Or {
+impl SetVariant {
     pub fn new(
-        tok: Token,
-        x1: Box<dyn ExprAble>,
-        x2: Box<dyn ExprAble>,
-        count: Rc<RefCell<u8>>,
-        labels: Rc<RefCell<u32>>,
-    ) -> Or {
-        Or {
-            logic: Logical::new(tok, x1, x2, count, labels),
+        i: Box<dyn ExprAble>,
+        v: usize,
+        xs: Vec<Box<dyn ExprAble>>,
+        line: u32,
+        count: Rc<RefCell<u32>>,
+    ) -> SetVariant {
+        let p = (*i).get_type().clone();
+        let variant = &p.variants().unwrap()[v];
+        let mut fields = Vec::new();
+        for (n, x) in xs.into_iter().enumerate() {
+            fields.push(match variant.fields.get(n) {
+                Some(f) => Set::convert(f, x, count.clone()).unwrap_or_else(|x| x),
+                None => x,
+            });
+        }
+
+        SetVariant {
+            expr_base: ExprBase::new(Token::Word(Word::Word(p.word.clone())), p),
+            id: i,
+            tag: v as u32,
+            fields,
+            line,
+            temp_count: count,
         }
     }
 }
 
-impl ExprAble for Or {
-    fn jumping(&self, t: u32, f: u32) {
-        let label: u32;
-        if t != 0 {
-            label = t;
-        } else {
-            label = new_label(self.logic.labels.clone());
+impl ExprAble for SetVariant {
+    fn gen(&self) -> Box<dyn ExprAble> {
+        let p = self.get_type();
+        let tag = Access::new((*self.id).gen(), 0, type_int(), self.temp_count.clone());
+        tag.store(&Constant::new(
+            Token::Num(Num::new(self.tag as u64)),
+            type_int(),
+        ));
+        for (i, x) in self.fields.iter().enumerate() {
+            let field = Access::new(
+                (*self.id).gen(),
+                p.field_offset(self.tag as usize, i),
+                (*x).get_type().clone(),
+                self.temp_count.clone(),
+            );
+            field.store(&**x);
         }
-        self.logic.expr1.jumping(label, 0);
-        self.logic.expr2.jumping(t, f);
-        if t == 0 {
-            emit_label(label);
+        (*self.id).gen()
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        self.gen()
+    }
+
+    fn to_string(&self) -> String {
+        let variant = &self.get_type().variants().unwrap()[self.tag as usize];
+        let xs: Vec<String> = self.fields.iter().map(|x| (*x).to_string()).collect();
+        format!(
+            "{} = {}({})",
+            (*self.id).to_string(),
+            variant.name,
+            xs.join(", ")
+        )
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        let variant = &self.get_type().variants().unwrap()[self.tag as usize];
+        for x in self.fields.iter() {
+            (*x).type_check(errors);
+        }
+        if self.fields.len() != variant.fields.len() {
+            errors.push((
+                self.line,
+                format!(
+                    "variant {} takes {} values but {} were given",
+                    variant.name,
+                    variant.fields.len(),
+                    self.fields.len()
+                ),
+            ));
+            return;
+        }
+        for (x, f) in self.fields.iter().zip(variant.fields.iter()) {
+            if let Some(e) = Set::mismatch(f, (*x).get_type()) {
+                errors.push((self.line, format!("{} in variant {}", e, variant.name)));
+            }
         }
     }
 
     // Explicitly inherited:
 
-    gen! {self, logic}
-    reduce! {self, logic}
-    emit_jumps! {self, logic}
-    to_string! {self, logic}
-    get_type! {self, logic}
+    jumping! {self, expr_base}
+    emit_jumps! {self, expr_base}
+    get_type! {self, expr_base}
 }
 
-pub struct Not {
-    logic: Logical,
+pub struct SetOp {
+    expr_base: ExprBase,
+    id: Box<dyn ExprAble>,
+    op: Token,
+    expr: Box<dyn ExprAble>,
+    line: u32,
+    temp_count: Rc<RefCell<u32>>,
 }
 
-impl Not {
+impl SetOp {
+    /// x op= y where tok is the arithmetic operator op
     pub fn new(
         tok: Token,
-        x2: Box<dyn ExprAble>,
-        count: Rc<RefCell<u8>>,
-        labels: Rc<RefCell<u32>>,
-    ) -> Not {
-        // I use Box::new(Id::new()) as an unuseful thing cause Logical requires 2 pointers
-        // TODO: rewrite it
+        i: Box<dyn ExprAble>,
+        x: Box<dyn ExprAble>,
+        line: u32,
+        count: Rc<RefCell<u32>>,
+    ) -> SetOp {
+        let p = (*i).get_type().clone();
+        let x = adopt(x, &p);
+        let type_ = if (*i).is_lvalue() && SetOp::check(tok.get_tag().unwrap(), &p, (*x).get_type())
+        {
+            p
+        } else {
+            type_invalid()
+        };
 
-        Not {
-            logic: Logical::new(
-                tok,
-                Box::new(Id::new(word_true(), type_bool(), 0)),
-                x2,
-                count,
-                labels,
-            ),
+        SetOp {
+            expr_base: ExprBase::new(tok.clone(), type_),
+            id: i,
+            op: tok,
+            expr: x,
+            line,
+            temp_count: count,
         }
     }
 }
 
-impl ExprAble for Not {
-    fn jumping(&self, t: u32, f: u32) {
-        (*self.logic.expr2).jumping(f, t);
+impl SetOp {
+    // The result of x op y is stored into x. A char is promoted to int in the operation, the
+    // result is converted back without a cast.
+    fn check(tag: u32, p1: &TypeBase, p2: &TypeBase) -> bool {
+        match Arith::check(tag, p1, p2) {
+            Some(result) => result == *p1 || (*p1 == type_char() && result == type_int()),
+            None => false,
+        }
+    }
+
+    // Stores the value x of the operation into the location loc of type p
+    fn store_back(
+        loc: &dyn ExprAble,
+        x: Arith,
+        p: &TypeBase,
+        line: u32,
+        count: Rc<RefCell<u32>>,
+    ) -> Box<dyn ExprAble> {
+        if *x.get_type() == *p {
+            loc.store(&x)
+        } else {
+            loc.store(&Cast::new(p.clone(), Box::new(x), line, count))
+        }
+    }
+}
+
+impl ExprAble for SetOp {
+    // The location is computed once, gen of a location does not emit anything
+    fn gen(&self) -> Box<dyn ExprAble> {
+        let loc = (*self.id).gen();
+        let x = Arith::new(
+            self.op.clone(),
+            loc.gen(),
+            (*self.expr).reduce(),
+            self.line,
+            self.temp_count.clone(),
+        );
+        SetOp::store_back(
+            &*loc,
+            x,
+            self.get_type(),
+            self.line,
+            self.temp_count.clone(),
+        )
+    }
+
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        self.gen()
     }
 
     fn to_string(&self) -> String {
         format!(
-            "{} {}",
-            self.logic.expr_base.op.to_string(),
-            self.logic.expr2.to_string()
+            "{} {}= {}",
+            (*self.id).to_string(),
+            self.op.to_string(),
+            (*self.expr).to_string()
         )
     }
 
+    fn assignments(&self) -> Option<Vec<(Term, Term)>> {
+        let value = match char::from_u32(self.op.get_tag().unwrap()) {
+            Some(c @ ('+' | '-' | '*')) if *self.get_type() == type_int() => Term::Arith(
+                c,
+                Box::new((*self.id).term()),
+                Box::new((*self.expr).term()),
+            ),
+            _ => Term::Unsupported(self.to_string()),
+        };
+        Some(vec![((*self.id).term(), value)])
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        (*self.id).type_check(errors);
+        (*self.expr).type_check(errors);
+        if let Some(e) = not_assignable(&*self.id) {
+            errors.push((self.line, e));
+            return;
+        }
+        let (p1, p2) = ((*self.id).get_type(), (*self.expr).get_type());
+        if *self.get_type() != type_invalid() || !valid(&[p1, p2]) {
+            return;
+        }
+        let tag = self.op.get_tag().unwrap();
+        let e = match Arith::check(tag, p1, p2) {
+            Some(result) => format!(
+                "implicit conversion from {} to {} loses precision, use an explicit cast",
+                result.word.lexeme, p1.word.lexeme
+            ),
+            None => format!(
+                "cannot {} {} and {}",
+                Arith::verb(tag),
+                p1.word.lexeme,
+                p2.word.lexeme
+            ),
+        };
+        errors.push((self.line, e));
+    }
+
     // Explicitly inherited:
-    gen! {self, logic}
-    reduce! {self, logic}
-    emit_jumps! {self, logic}
-    get_type! {self, logic}
-}
 
-pub struct Rel {
-    logic: Logical,
+    jumping! {self, expr_base}
+    emit_jumps! {self, expr_base}
+    get_type! {self, expr_base}
 }
 
-impl Rel {
-    pub fn new(
-        tok: Token,
-        x1: Box<dyn ExprAble>,
-        x2: Box<dyn ExprAble>,
-        count: Rc<RefCell<u8>>,
-        labels: Rc<RefCell<u32>>,
-    ) -> Rel {
-        Rel {
-            logic: logical_construct!(Rel::check, tok, x1, x2, count, labels),
-        }
-    }
+pub struct Postfix {
+    expr_base: ExprBase,
+    id: Box<dyn ExprAble>,
+    op: Token,
+    line: u32,
+    temp_count: Rc<RefCell<u32>>,
+}
 
-    fn check(p1: &TypeBase, p2: &TypeBase) -> bool {
-        if *p1 == *p2 {
-            true
+impl Postfix {
+    /// x++ and x-- where tok is + or -, the value is the one x had before
+    pub fn new(tok: Token, i: Box<dyn ExprAble>, line: u32, count: Rc<RefCell<u32>>) -> Postfix {
+        let p = (*i).get_type().clone();
+        let one = if integral(&p) { p.clone() } else { type_int() };
+        let type_ = if (*i).is_lvalue() && SetOp::check(tok.get_tag().unwrap(), &p, &one) {
+            p
         } else {
-            false
+            type_invalid()
+        };
+
+        Postfix {
+            expr_base: ExprBase::new(tok.clone(), type_),
+            id: i,
+            op: tok,
+            line,
+            temp_count: count,
         }
     }
 }
 
-impl ExprAble for Rel {
+impl ExprAble for Postfix {
     fn gen(&self) -> Box<dyn ExprAble> {
-        logical_gen!(self, self.logic.labels, self.logic.temp_count)
+        let loc = (*self.id).gen();
+        let old = Temp::new(self.get_type().clone(), self.temp_count.clone());
+        emit(format!("{} = {}", old.to_string(), loc.to_string()));
+        let x = Arith::new(
+            self.op.clone(),
+            Box::new(old.clone()),
+            Box::new(Constant::new(Token::Num(Num::new(1)), type_int())),
+            self.line,
+            self.temp_count.clone(),
+        );
+        SetOp::store_back(
+            &*loc,
+            x,
+            self.get_type(),
+            self.line,
+            self.temp_count.clone(),
+        );
+        Box::new(old)
     }
 
-    fn jumping(&self, t: u32, f: u32) {
-        let a = self.logic.expr1.reduce();
-        let b = self.logic.expr2.reduce();
-        let test = a.to_string()
-            + " "
-            + &(*self.logic.expr_base.get_op()).to_string()
-            + " "
-            + &b.to_string();
-        self.emit_jumps(test, t, f);
+    fn reduce(&self) -> Box<dyn ExprAble> {
+        self.gen()
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "{}{}{}",
+            (*self.id).to_string(),
+            self.op.to_string(),
+            self.op.to_string()
+        )
+    }
+
+    fn assignments(&self) -> Option<Vec<(Term, Term)>> {
+        let value = match char::from_u32(self.op.get_tag().unwrap()) {
+            Some(c) if *self.get_type() == type_int() => {
+                Term::Arith(c, Box::new((*self.id).term()), Box::new(Term::Num(1)))
+            }
+            _ => Term::Unsupported(self.to_string()),
+        };
+        Some(vec![((*self.id).term(), value)])
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        (*self.id).type_check(errors);
+        let p = (*self.id).get_type();
+        if let Some(e) = not_assignable(&*self.id) {
+            errors.push((self.line, e));
+        } else if *self.get_type() == type_invalid() && valid(&[p]) {
+            let what = if self.op.get_tag() == Some('+' as u32) {
+                "increment"
+            } else {
+                "decrement"
+            };
+            errors.push((self.line, format!("cannot {} {}", what, p.word.lexeme)));
+        }
     }
 
     // Explicitly inherited:
 
-    reduce! {self, logic}
-    emit_jumps! {self, logic}
-    to_string! {self, logic}
-    get_type! {self, logic}
+    jumping! {self, expr_base}
+    emit_jumps! {self, expr_base}
+    get_type! {self, expr_base}
 }
 
 // Statements:
@@ -672,6 +2508,14 @@ pub trait StmtAble {
     fn init(&mut self, _x: Box<dyn ExprAble>, _s: Box<dyn StmtAble>) {
         unreachable!();
     }
+
+    // The statement as the verifier sees it, see verify.rs
+
+    fn command(&self) -> Command;
+
+    // Checks the types of the expressions of the statement, see ExprAble::type_check
+
+    fn type_check(&self, _errors: &mut TypeErrors) {}
 }
 
 pub struct Null {}
@@ -680,14 +2524,73 @@ impl StmtAble for Null {
     fn is_null(&self) -> bool {
         true
     }
+
+    fn command(&self) -> Command {
+        Command::Skip
+    }
 }
 
-pub struct Break {}
+pub struct Break {
+    pub line: u32,
+}
 
 impl StmtAble for Break {
     fn gen(&self, _b: u32, _a: u32, gen_after: u32) {
         emit(format!("goto L{}", gen_after));
     }
+
+    fn command(&self) -> Command {
+        Command::Unsupported("break".to_string(), self.line, false)
+    }
+}
+
+pub struct Goto {
+    target: Rc<Cell<u32>>,
+    line: u32,
+}
+
+impl Goto {
+    pub fn new(target: Rc<Cell<u32>>, line: u32) -> Goto {
+        Goto { target, line }
+    }
+}
+
+impl StmtAble for Goto {
+    fn gen(&self, _b: u32, _a: u32, _gen_after: u32) {
+        emit(format!("goto L{}", self.target.get()));
+    }
+
+    // Never skipped, a goto may jump back to annotations the verifier has already passed
+    fn command(&self) -> Command {
+        Command::Unsupported("goto".to_string(), self.line, true)
+    }
+}
+
+// Statement with a label in front of it, the label is the target of gotos
+pub struct Labeled {
+    label: u32,
+    stmt: Box<dyn StmtAble>,
+}
+
+impl Labeled {
+    pub fn new(label: u32, stmt: Box<dyn StmtAble>) -> Labeled {
+        Labeled { label, stmt }
+    }
+}
+
+impl StmtAble for Labeled {
+    fn gen(&self, b: u32, a: u32, gen_after: u32) {
+        emit_label(self.label);
+        (*self.stmt).gen(b, a, gen_after);
+    }
+
+    fn command(&self) -> Command {
+        (*self.stmt).command()
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        (*self.stmt).type_check(errors);
+    }
 }
 
 pub struct Seq {
@@ -719,50 +2622,265 @@ impl StmtAble for Seq {
             (*self.stmt2).gen(label, a, gen_after);
         }
     }
+
+    fn command(&self) -> Command {
+        Command::Seq(
+            Box::new((*self.stmt1).command()),
+            Box::new((*self.stmt2).command()),
+        )
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        (*self.stmt1).type_check(errors);
+        (*self.stmt2).type_check(errors);
+    }
 }
 
-pub struct Set {
-    id: Box<dyn ExprAble>,
+pub struct ExprStmt {
     expr: Box<dyn ExprAble>,
+    line: u32,
 }
 
-impl Set {
-    pub fn new(i: Box<dyn ExprAble>, x: Box<dyn ExprAble>) -> Set {
-        let p1 = (*i).get_type();
-        let p2 = (*x).get_type();
+impl ExprStmt {
+    pub fn new(x: Box<dyn ExprAble>, line: u32) -> ExprStmt {
+        ExprStmt { expr: x, line }
+    }
+}
 
-        if numeric(p1) && numeric(p2) {
-        } else if *p1 == type_bool() && *p2 == type_bool() {
-        } else {
-            panic!("type error");
+impl StmtAble for ExprStmt {
+    fn gen(&self, _b: u32, _a: u32, _gen_after: u32) {
+        (*self.expr).gen();
+    }
+
+    fn command(&self) -> Command {
+        match (*self.expr).assignments() {
+            Some(pairs) => Command::Assign(pairs, self.line),
+            None => Command::Eval(vec![(*self.expr).term()], self.line),
+        }
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        (*self.expr).type_check(errors);
+    }
+}
+
+// Only values of the basic types can be printed and read
+fn io_check(x: &dyn ExprAble, what: &str, line: u32, errors: &mut TypeErrors) {
+    x.type_check(errors);
+    let p = x.get_type();
+    if valid(&[p]) && !(numeric(p) || *p == type_bool() || *p == type_char()) {
+        errors.push((line, format!("cannot {} {}", what, p.word.lexeme)));
+    }
+}
+
+/// print(x, ...) writes the values on one line, each with a print_<type> instruction followed by
+/// print_newline
+pub struct Print {
+    exprs: Vec<Box<dyn ExprAble>>,
+    line: u32,
+}
+
+impl Print {
+    pub fn new(xs: Vec<Box<dyn ExprAble>>, line: u32) -> Print {
+        Print { exprs: xs, line }
+    }
+}
+
+impl StmtAble for Print {
+    fn gen(&self, _b: u32, _a: u32, _gen_after: u32) {
+        for x in self.exprs.iter() {
+            let a = (*x).reduce();
+            emit(format!(
+                "print_{} {}",
+                (*x).get_type().word.lexeme,
+                a.to_string()
+            ));
         }
+        emit("print_newline".to_string());
+    }
+
+    fn command(&self) -> Command {
+        Command::Eval(self.exprs.iter().map(|x| (*x).term()).collect(), self.line)
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        for x in self.exprs.iter() {
+            io_check(&**x, "print", self.line, errors);
+        }
+    }
+}
+
+/// read(x) reads a value of the type of x into a temporary with a read_<type> instruction and
+/// assigns it
+pub struct Read {
+    id: Box<dyn ExprAble>,
+    line: u32,
+    temp_count: Rc<RefCell<u32>>,
+}
 
-        Set { id: i, expr: x }
+impl Read {
+    pub fn new(i: Box<dyn ExprAble>, line: u32, count: Rc<RefCell<u32>>) -> Read {
+        Read {
+            id: i,
+            line,
+            temp_count: count,
+        }
     }
 }
 
-impl StmtAble for Set {
+impl StmtAble for Read {
     fn gen(&self, _b: u32, _a: u32, _gen_after: u32) {
+        let loc = (*self.id).gen();
+        let t = Temp::new((*loc.get_type()).clone(), self.temp_count.clone());
         emit(format!(
-            "{} = {}",
-            (*self.id).to_string(),
-            (*(*self.expr).gen()).to_string()
+            "read_{} {}",
+            t.get_type().word.lexeme,
+            t.to_string()
         ));
+        loc.store(&t);
+    }
+
+    fn command(&self) -> Command {
+        Command::Havoc((*self.id).term(), self.line)
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        io_check(&*self.id, "read", self.line, errors);
+        if let Some(e) = not_assignable(&*self.id) {
+            errors.push((self.line, e));
+        }
+    }
+}
+
+/// One arm of a match: the variant, the variables bound to its data and the statement
+pub struct Arm {
+    pub tag: u32,
+    pub ids: Vec<Option<Id>>,
+    pub stmt: Box<dyn StmtAble>,
+}
+
+pub struct Match {
+    id: Box<dyn ExprAble>,
+    arms: Vec<Arm>,
+    line: u32,
+    temp_count: Rc<RefCell<u32>>,
+    labels: Rc<RefCell<u32>>,
+}
+
+impl Match {
+    pub fn new(
+        i: Box<dyn ExprAble>,
+        arms: Vec<Arm>,
+        line: u32,
+        count: Rc<RefCell<u32>>,
+        labels: Rc<RefCell<u32>>,
+    ) -> Match {
+        Match {
+            id: i,
+            arms,
+            line,
+            temp_count: count,
+            labels,
+        }
+    }
+}
+
+impl StmtAble for Match {
+    // Dispatches on the tag, the last arm needs no test as the match is exhaustive
+    fn gen(&self, _b: u32, a: u32, gen_after: u32) {
+        let p = (*self.id).get_type();
+        let tag = Access::new((*self.id).gen(), 0, type_int(), self.temp_count.clone()).reduce();
+
+        for (n, arm) in self.arms.iter().enumerate() {
+            let last = n + 1 == self.arms.len();
+            let next = if last {
+                0
+            } else {
+                new_label(self.labels.clone())
+            };
+            if !last {
+                emit(format!(
+                    "iffalse {} == {} goto L{}",
+                    tag.to_string(),
+                    arm.tag,
+                    next
+                ));
+            }
+            for (i, id) in arm.ids.iter().enumerate() {
+                if let Some(id) = id {
+                    let field = Access::new(
+                        (*self.id).gen(),
+                        p.field_offset(arm.tag as usize, i),
+                        (*id.get_type()).clone(),
+                        self.temp_count.clone(),
+                    );
+                    id.store(&field);
+                }
+            }
+            let label = new_label(self.labels.clone());
+            emit_label(label);
+            (*arm.stmt).gen(label, a, gen_after);
+            if !last {
+                emit(format!("goto L{}", a));
+                emit_label(next);
+            }
+        }
+    }
+
+    fn command(&self) -> Command {
+        let annotations = self
+            .arms
+            .iter()
+            .any(|arm| annotated(&(*arm.stmt).command()));
+        Command::Unsupported("match".to_string(), self.line, annotations)
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        (*self.id).type_check(errors);
+        for arm in self.arms.iter() {
+            (*arm.stmt).type_check(errors);
+        }
+
+        let p = (*self.id).get_type();
+        let variants = match p.variants() {
+            Some(variants) => variants,
+            None if valid(&[p]) => {
+                let e = format!("union required in match, found {}", p.word.lexeme);
+                errors.push((self.line, e));
+                return;
+            }
+            None => return,
+        };
+        let missing: Vec<&str> = variants
+            .iter()
+            .enumerate()
+            .filter(|(v, _)| !self.arms.iter().any(|arm| arm.tag as usize == *v))
+            .map(|(_, variant)| variant.name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            let e = format!("match is not exhaustive, missing {}", missing.join(", "));
+            errors.push((self.line, e));
+        }
     }
 }
 
 pub struct If {
     expr: Box<dyn ExprAble>,
     stmt: Box<dyn StmtAble>,
+    line: u32,
     labels: Rc<RefCell<u32>>,
 }
 
-macro_rules! bool_check {
-    ( $x:ident, $line:expr ) => {
-        if *(*$x).get_type() != type_bool() {
-            error("boolean required in if", $line);
-        }
-    };
+// Checks the condition x of a statement, it has to be a bool
+fn bool_check(x: &dyn ExprAble, line: u32, stmt: &str, errors: &mut TypeErrors) {
+    x.type_check(errors);
+    let p = x.get_type();
+    if valid(&[p]) && *p != type_bool() {
+        errors.push((
+            line,
+            format!("boolean required in {}, found {}", stmt, p.word.lexeme),
+        ));
+    }
 }
 
 impl If {
@@ -772,10 +2890,10 @@ impl If {
         line: u32,
         labels: Rc<RefCell<u32>>,
     ) -> If {
-        bool_check!(x, line);
         If {
             expr: x,
             stmt: s,
+            line,
             labels: labels,
         }
     }
@@ -788,12 +2906,27 @@ impl StmtAble for If {
         emit_label(label);
         (*self.stmt).gen(label, a, gen_after);
     }
+
+    fn command(&self) -> Command {
+        Command::If(
+            (*self.expr).term(),
+            Box::new((*self.stmt).command()),
+            Box::new(Command::Skip),
+            self.line,
+        )
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        bool_check(&*self.expr, self.line, "if", errors);
+        (*self.stmt).type_check(errors);
+    }
 }
 
 pub struct Else {
     expr: Box<dyn ExprAble>,
     stmt1: Box<dyn StmtAble>,
     stmt2: Box<dyn StmtAble>,
+    line: u32,
     labels: Rc<RefCell<u32>>,
 }
 
@@ -805,11 +2938,11 @@ impl Else {
         line: u32,
         labels: Rc<RefCell<u32>>,
     ) -> Else {
-        bool_check!(x, line);
         Else {
             expr: x,
             stmt1: s1,
             stmt2: s2,
+            line,
             labels: labels,
         }
     }
@@ -826,11 +2959,27 @@ impl StmtAble for Else {
         emit_label(label2);
         (*self.stmt2).gen(label2, a, gen_after);
     }
+
+    fn command(&self) -> Command {
+        Command::If(
+            (*self.expr).term(),
+            Box::new((*self.stmt1).command()),
+            Box::new((*self.stmt2).command()),
+            self.line,
+        )
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        bool_check(&*self.expr, self.line, "if", errors);
+        (*self.stmt1).type_check(errors);
+        (*self.stmt2).type_check(errors);
+    }
 }
 
 pub struct While {
     expr: Option<Box<dyn ExprAble>>,
     stmt: Option<Box<dyn StmtAble>>,
+    invariants: Annotations,
     line: u32,
     labels: Rc<RefCell<u32>>,
 }
@@ -840,10 +2989,16 @@ impl While {
         While {
             expr: None,
             stmt: None,
+            invariants: Vec::new(),
             line: line,
             labels: labels,
         }
     }
+
+    // invariant(x) holds before every test of the loop condition, only the verifier uses it
+    pub fn invariant(&mut self, x: Box<dyn ExprAble>, line: u32) {
+        self.invariants.push((x, line));
+    }
 }
 
 impl StmtAble for While {
@@ -856,8 +3011,115 @@ impl StmtAble for While {
     }
 
     fn init(&mut self, x: Box<dyn ExprAble>, s: Box<dyn StmtAble>) {
-        bool_check!(x, self.line);
         self.expr = Some(x);
         self.stmt = Some(s);
     }
+
+    fn command(&self) -> Command {
+        Command::While {
+            cond: self.expr.as_ref().unwrap().term(),
+            invariants: self
+                .invariants
+                .iter()
+                .map(|(x, line)| ((*x).term(), *line))
+                .collect(),
+            body: Box::new(self.stmt.as_ref().unwrap().command()),
+            line: self.line,
+        }
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        let x = self.expr.as_ref().unwrap();
+        bool_check(&**x, self.line, "while", errors);
+        for (x, line) in self.invariants.iter() {
+            bool_check(&**x, *line, "invariant", errors);
+        }
+        self.stmt.as_ref().unwrap().type_check(errors);
+    }
+}
+
+// assert(x) branches around a trap that stops the program when x is false
+pub struct Assert {
+    expr: Box<dyn ExprAble>,
+    line: u32,
+}
+
+impl Assert {
+    pub fn new(x: Box<dyn ExprAble>, line: u32) -> Assert {
+        Assert { expr: x, line }
+    }
+}
+
+impl StmtAble for Assert {
+    fn gen(&self, _b: u32, a: u32, _gen_after: u32) {
+        (*self.expr).jumping(a, 0);
+        emit(format!("trap {}", self.line));
+    }
+
+    fn command(&self) -> Command {
+        Command::Assert((*self.expr).term(), self.line)
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        bool_check(&*self.expr, self.line, "assert", errors);
+    }
+}
+
+/// Annotations of a block or a loop with the lines they are written on
+pub type Annotations = Vec<(Box<dyn ExprAble>, u32)>;
+
+/// Block with requires(x); and ensures(x); annotations, they are only read by the verifier
+pub struct Contract {
+    requires: Annotations,
+    ensures: Annotations,
+    stmt: Box<dyn StmtAble>,
+    entry: bool,
+}
+
+impl Contract {
+    /// entry is set for the outermost block of a module, its requires are assumed rather than
+    /// checked
+    pub fn new(
+        requires: Annotations,
+        ensures: Annotations,
+        s: Box<dyn StmtAble>,
+        entry: bool,
+    ) -> Contract {
+        Contract {
+            requires,
+            ensures,
+            stmt: s,
+            entry,
+        }
+    }
+}
+
+impl StmtAble for Contract {
+    fn gen(&self, b: u32, a: u32, gen_after: u32) {
+        (*self.stmt).gen(b, a, gen_after);
+    }
+
+    fn is_null(&self) -> bool {
+        (*self.stmt).is_null()
+    }
+
+    fn command(&self) -> Command {
+        let terms = |xs: &Annotations| xs.iter().map(|(x, line)| ((*x).term(), *line)).collect();
+        Command::Block {
+            requires: terms(&self.requires),
+            ensures: terms(&self.ensures),
+            body: Box::new((*self.stmt).command()),
+            entry: self.entry,
+        }
+    }
+
+    fn type_check(&self, errors: &mut TypeErrors) {
+        for (x, line) in self.requires.iter() {
+            bool_check(&**x, *line, "requires", errors);
+        }
+        for (x, line) in self.ensures.iter() {
+            bool_check(&**x, *line, "ensures", errors);
+        }
+        (*self.stmt).type_check(errors);
+    }
 }
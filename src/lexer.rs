@@ -5,24 +5,49 @@ use std::io::Read;
 
 /// Enumeration Tag represents token types except for symbols such {, }, etc.
 pub enum Tag {
-    And = 256,
+    AddAssign = 256,
+    And,
+    Arrow,
+    Assert,
     Basic, // primitive types such as char, bool, int, float and array
+    BigNum,
     Break,
+    Char,
+    Dec,
+    DivAssign,
     Do,
     Else,
+    Ensures,
+    Enum,
     Eq_,
     False,
     Ge,
+    Goto,
     Id,
     If,
+    Import,
+    Inc,
+    Invariant,
     Le,
+    Match,
     Minus,
+    Module,
+    MulAssign,
     Ne,
     Num,
     Or,
+    Print,
+    Read,
     Real,
+    Requires,
+    Shl,
+    Shr,
+    SubAssign,
     Temp,
     True,
+    Type,
+    Union,
+    Var,
     While,
 }
 
@@ -87,6 +112,61 @@ fn word_eq() -> WordBase {
     WordBase::new("==".to_string(), Tag::Eq_ as u32)
 }
 
+#[inline]
+fn word_arrow() -> WordBase {
+    WordBase::new("=>".to_string(), Tag::Arrow as u32)
+}
+
+#[inline]
+fn word_le() -> WordBase {
+    WordBase::new("<=".to_string(), Tag::Le as u32)
+}
+
+#[inline]
+fn word_ge() -> WordBase {
+    WordBase::new(">=".to_string(), Tag::Ge as u32)
+}
+
+#[inline]
+fn word_shl() -> WordBase {
+    WordBase::new("<<".to_string(), Tag::Shl as u32)
+}
+
+#[inline]
+fn word_shr() -> WordBase {
+    WordBase::new(">>".to_string(), Tag::Shr as u32)
+}
+
+#[inline]
+fn word_add_assign() -> WordBase {
+    WordBase::new("+=".to_string(), Tag::AddAssign as u32)
+}
+
+#[inline]
+fn word_sub_assign() -> WordBase {
+    WordBase::new("-=".to_string(), Tag::SubAssign as u32)
+}
+
+#[inline]
+fn word_mul_assign() -> WordBase {
+    WordBase::new("*=".to_string(), Tag::MulAssign as u32)
+}
+
+#[inline]
+fn word_div_assign() -> WordBase {
+    WordBase::new("/=".to_string(), Tag::DivAssign as u32)
+}
+
+#[inline]
+fn word_inc() -> WordBase {
+    WordBase::new("++".to_string(), Tag::Inc as u32)
+}
+
+#[inline]
+fn word_dec() -> WordBase {
+    WordBase::new("--".to_string(), Tag::Dec as u32)
+}
+
 #[inline]
 pub fn word_true() -> WordBase {
     WordBase::new("true".to_string(), Tag::True as u32)
@@ -99,17 +179,17 @@ pub fn word_false() -> WordBase {
 
 #[inline]
 pub fn word_minus() -> WordBase {
-    WordBase::new("minus".to_string(), Tag::Minus as u32)
+    WordBase::new("-".to_string(), Tag::Minus as u32)
 }
 
 #[derive(Clone)]
 pub struct Num {
     token: TokenBase,
-    pub value: u32,
+    pub value: u64,
 }
 
 impl Num {
-    pub fn new(v: u32) -> Num {
+    pub fn new(v: u64) -> Num {
         Num {
             token: TokenBase {
                 tag: Tag::Num as u32,
@@ -117,6 +197,45 @@ impl Num {
             value: v,
         }
     }
+
+    /// Character literal, its value is the character code
+    pub fn char(c: char) -> Num {
+        Num {
+            token: TokenBase {
+                tag: Tag::Char as u32,
+            },
+            value: c as u64,
+        }
+    }
+
+    /// An integer literal is an int unless its value needs a wider type
+    pub fn get_type(&self) -> TypeBase {
+        if self.value <= type_int().max_value() {
+            type_int()
+        } else if self.value <= type_i64().max_value() {
+            type_i64()
+        } else {
+            type_u64()
+        }
+    }
+}
+
+/// Integer literal of a bigint, kept as its decimal digits
+#[derive(Clone)]
+pub struct BigNum {
+    token: TokenBase,
+    pub digits: String,
+}
+
+impl BigNum {
+    pub fn new(digits: String) -> BigNum {
+        BigNum {
+            token: TokenBase {
+                tag: Tag::BigNum as u32,
+            },
+            digits,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -136,10 +255,29 @@ impl Real {
     }
 }
 
+/// TypeKind tells basic types from constructed ones, a constructed type keeps the type it is
+/// built from.
+#[derive(Clone)]
+pub enum TypeKind {
+    Basic,
+    Pointer(Box<TypeBase>),
+    Enum,
+    Union(Vec<Variant>),
+    Tuple(Vec<TypeBase>),
+}
+
+/// Variant of a tagged union with the types of the data it carries
+#[derive(Clone)]
+pub struct Variant {
+    pub name: String,
+    pub fields: Vec<TypeBase>,
+}
+
 #[derive(Clone)]
 pub struct TypeBase {
     pub word: WordBase,
     width: u32,
+    pub kind: TypeKind,
 }
 
 impl PartialEq for TypeBase {
@@ -156,6 +294,62 @@ pub fn type_int() -> TypeBase {
     TypeBase {
         word: WordBase::new("int".to_string(), Tag::Basic as u32),
         width: 4,
+        kind: TypeKind::Basic,
+    }
+}
+
+// Sized integer types, int is the signed 32-bit one and i32 is another name for it
+#[inline]
+fn type_sized(name: &str, width: u32) -> TypeBase {
+    TypeBase {
+        word: WordBase::new(name.to_string(), Tag::Basic as u32),
+        width,
+        kind: TypeKind::Basic,
+    }
+}
+
+#[inline]
+pub fn type_i8() -> TypeBase {
+    type_sized("i8", 1)
+}
+
+#[inline]
+pub fn type_i16() -> TypeBase {
+    type_sized("i16", 2)
+}
+
+#[inline]
+pub fn type_i64() -> TypeBase {
+    type_sized("i64", 8)
+}
+
+#[inline]
+pub fn type_u8() -> TypeBase {
+    type_sized("u8", 1)
+}
+
+#[inline]
+pub fn type_u16() -> TypeBase {
+    type_sized("u16", 2)
+}
+
+#[inline]
+pub fn type_u32() -> TypeBase {
+    type_sized("u32", 4)
+}
+
+#[inline]
+pub fn type_u64() -> TypeBase {
+    type_sized("u64", 8)
+}
+
+/// A bigint value is a handle to a number kept by the runtime
+#[inline]
+pub fn type_bigint() -> TypeBase {
+    TypeBase {
+        word: WordBase::new("bigint".to_string(), Tag::Basic as u32),
+        width: 8,
+        kind: TypeKind::Basic,
     }
 }
 
@@ -164,6 +358,7 @@ pub fn type_float() -> TypeBase {
     TypeBase {
         word: WordBase::new("float".to_string(), Tag::Basic as u32),
         width: 8,
+        kind: TypeKind::Basic,
     }
 }
 
@@ -172,6 +367,7 @@ pub fn type_char() -> TypeBase {
     TypeBase {
         word: WordBase::new("char".to_string(), Tag::Basic as u32),
         width: 1,
+        kind: TypeKind::Basic,
     }
 }
 
@@ -180,33 +376,230 @@ pub fn type_bool() -> TypeBase {
     TypeBase {
         word: WordBase::new("bool".to_string(), Tag::Basic as u32),
         width: 1,
+        kind: TypeKind::Basic,
     }
 }
 
 #[inline]
-pub fn numeric(p: &TypeBase) -> bool {
-    if *p == type_int() || *p == type_float() {
-        true
-    } else {
-        false
+pub fn type_pointer(p: &TypeBase) -> TypeBase {
+    TypeBase {
+        word: WordBase::new(format!("{}*", p.word.lexeme), Tag::Basic as u32),
+        width: 8,
+        kind: TypeKind::Pointer(Box::new(p.clone())),
     }
 }
 
+/// Type of an ill-typed expression. The type checker reports the error where it arises and nothing
+/// about the expressions around it.
+#[inline]
+pub fn type_invalid() -> TypeBase {
+    TypeBase {
+        word: WordBase::new("<invalid>".to_string(), Tag::Basic as u32),
+        width: 0,
+        kind: TypeKind::Basic,
+    }
+}
+
+/// Every enumeration is a type of its own whose values are stored like int ones
+#[inline]
+pub fn type_enum(name: &str) -> TypeBase {
+    TypeBase {
+        word: WordBase::new(name.to_string(), Tag::Basic as u32),
+        width: 4,
+        kind: TypeKind::Enum,
+    }
+}
+
+/// Rounds offset up to a multiple of align
+#[inline]
+pub fn align_to(offset: u32, align: u32) -> u32 {
+    offset.div_ceil(align) * align
+}
+
+// Offsets of values laid out one after another from start, each at its natural alignment,
+// and the offset right after the last one
+fn lay_out(start: u32, types: &[TypeBase]) -> (Vec<u32>, u32) {
+    let mut offsets = Vec::new();
+    let mut end = start;
+    for p in types {
+        let offset = align_to(end, p.align());
+        offsets.push(offset);
+        end = offset + p.get_width();
+    }
+    (offsets, end)
+}
+
+/// A tagged union is laid out as an int tag followed by room for the largest variant, its width
+/// is a multiple of its alignment
+pub fn type_union(name: &str, variants: Vec<Variant>) -> TypeBase {
+    let mut p = TypeBase {
+        word: WordBase::new(name.to_string(), Tag::Basic as u32),
+        width: 0,
+        kind: TypeKind::Union(variants),
+    };
+    let end = p
+        .variants()
+        .unwrap()
+        .iter()
+        .map(|v| lay_out(type_int().get_width(), &v.fields).1);
+    p.width = align_to(end.max().unwrap_or(0), p.align());
+    p
+}
+
+/// A tuple is laid out as its elements one after another, each at its natural alignment
+pub fn type_tuple(elements: Vec<TypeBase>) -> TypeBase {
+    let names: Vec<&str> = elements.iter().map(|p| p.word.lexeme.as_str()).collect();
+    let mut p = TypeBase {
+        word: WordBase::new(format!("({})", names.join(", ")), Tag::Basic as u32),
+        width: 0,
+        kind: TypeKind::Tuple(elements),
+    };
+    p.width = align_to(lay_out(0, p.elements().unwrap()).1, p.align());
+    p
+}
+
+#[inline]
+pub fn numeric(p: &TypeBase) -> bool {
+    integral(p) || *p == type_float() || *p == type_char() || *p == type_bigint()
+}
+
+#[inline]
+pub fn integral(p: &TypeBase) -> bool {
+    p.signed().is_some()
+}
+
 impl TypeBase {
     #[inline]
     pub fn get_width(&self) -> u32 {
         self.width
     }
 
+    /// Natural alignment, the width of a scalar and the largest alignment of the parts of an
+    /// aggregate
+    pub fn align(&self) -> u32 {
+        match &self.kind {
+            TypeKind::Union(variants) => variants
+                .iter()
+                .flat_map(|v| v.fields.iter())
+                .map(|p| p.align())
+                .fold(type_int().align(), u32::max),
+            TypeKind::Tuple(elements) => elements.iter().map(|p| p.align()).fold(1, u32::max),
+            // The invalid type takes no room
+            _ => self.width.max(1),
+        }
+    }
+
+    /// Returns the type pointed to if this is a pointer type
+    pub fn pointee(&self) -> Option<&TypeBase> {
+        match &self.kind {
+            TypeKind::Pointer(p) => Some(p),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_enum(&self) -> bool {
+        matches!(self.kind, TypeKind::Enum)
+    }
+
+    /// Returns the variants if this is a tagged union
+    pub fn variants(&self) -> Option<&Vec<Variant>> {
+        match &self.kind {
+            TypeKind::Union(variants) => Some(variants),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_union(&self) -> bool {
+        self.variants().is_some()
+    }
+
+    /// Offset of field i of variant v inside a tagged union, the tag is at offset 0
+    pub fn field_offset(&self, v: usize, i: usize) -> u32 {
+        let fields = &self.variants().unwrap()[v].fields;
+        lay_out(type_int().get_width(), fields).0[i]
+    }
+
+    /// Returns the element types if this is a tuple
+    pub fn elements(&self) -> Option<&Vec<TypeBase>> {
+        match &self.kind {
+            TypeKind::Tuple(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_tuple(&self) -> bool {
+        self.elements().is_some()
+    }
+
+    /// Unions and tuples are not values of their own, they are assigned part by part
+    #[inline]
+    pub fn is_aggregate(&self) -> bool {
+        self.is_union() || self.is_tuple()
+    }
+
+    /// Offset of element i inside a tuple
+    pub fn element_offset(&self, i: usize) -> u32 {
+        lay_out(0, self.elements().unwrap()).0[i]
+    }
+
+    /// Returns whether an integer type is signed, None if the type is not an integer one
+    pub fn signed(&self) -> Option<bool> {
+        match self.word.lexeme.as_str() {
+            "int" | "i8" | "i16" | "i64" => Some(true),
+            "u8" | "u16" | "u32" | "u64" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Integer promotion, a char takes part in arithmetic as an int
+    pub fn promote(&self) -> TypeBase {
+        if *self == type_char() {
+            type_int()
+        } else {
+            self.clone()
+        }
+    }
+
+    /// Largest value of an integer type or of a char
+    pub fn max_value(&self) -> u64 {
+        let bits = 8 * self.width;
+        if self.signed() == Some(true) {
+            (1 << (bits - 1)) - 1
+        } else {
+            u64::MAX >> (64 - bits)
+        }
+    }
+
+    /// Returns the type both operands of a binary operator are converted to. A char is promoted
+    /// to int first. For integers of the same signedness it is the wider one. When signedness
+    /// differs the unsigned type wins unless the signed one is wider and so can hold all of its
+    /// values. Any integer mixed with a bigint becomes a bigint, there is no implicit conversion
+    /// between bigint and float.
     pub fn max(p1: &TypeBase, p2: &TypeBase) -> Option<TypeBase> {
         if !numeric(p1) || !numeric(p2) {
-            None
+            return None;
+        }
+        let (p1, p2) = (&p1.promote(), &p2.promote());
+        if *p1 == type_bigint() || *p2 == type_bigint() {
+            if *p1 == type_float() || *p2 == type_float() {
+                None
+            } else {
+                Some(type_bigint())
+            }
         } else if *p1 == type_float() || *p2 == type_float() {
             Some(type_float())
-        } else if *p1 == type_int() || *p2 == type_int() {
-            Some(type_int())
+        } else if p1.signed() == p2.signed() {
+            Some(if p1.width >= p2.width { p1 } else { p2 }.clone())
         } else {
-            Some(type_char())
+            let (s, u) = if p1.signed() == Some(true) {
+                (p1, p2)
+            } else {
+                (p2, p1)
+            };
+            Some(if u.width >= s.width { u } else { s }.clone())
         }
     }
 }
@@ -223,6 +616,7 @@ pub enum Token {
     Word(Word),
     Num(Num),
     Real(Real),
+    BigNum(BigNum),
     Eof,
 }
 
@@ -236,6 +630,7 @@ impl Token {
             },
             Token::Num(num) => Some(num.token.tag),
             Token::Real(real) => Some(real.token.tag),
+            Token::BigNum(big) => Some(big.token.tag),
             Token::Eof => None,
         }
     }
@@ -253,14 +648,32 @@ impl Token {
             },
             Token::Num(c) => format!("{}", c.value),
             Token::Real(d) => format!("{}", d.value),
+            Token::BigNum(big) => big.digits.clone(),
             _ => panic!(),
         }
     }
 }
 
+/// Source range of a name, lines and columns start at 1
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Span {
+    pub line: u32,
+    pub col: u32,
+    pub len: u32,
+}
+
+impl Span {
+    pub fn contains(&self, line: u32, col: u32) -> bool {
+        self.line == line && self.col <= col && col < self.col + self.len
+    }
+}
+
 pub struct Lexer {
     buf_reader: BufReader<File>,
-    pub line_num: u32, // uses for syntax error reports
+    pub line_num: u32,     // uses for syntax error reports
+    pub start: (u32, u32), // line and column of the last token scanned
+    col: u32,              // column of peek
+    after_dot: bool,       // digits after a dot are a tuple index, not a fraction
     peek: char,
     eof: bool,
     words: HashMap<String, Word>,
@@ -282,6 +695,9 @@ impl Lexer {
         let mut lex = Lexer {
             buf_reader: BufReader::new(File::open(file_name).expect("open failed")),
             line_num: 1,
+            start: (1, 1),
+            col: 0,
+            after_dot: false,
             peek: ' ',
             eof: false,
             words: HashMap::new(),
@@ -302,13 +718,79 @@ impl Lexer {
             Tag::Break as u32,
         )));
 
+        lex.reserve(Word::Word(WordBase::new(
+            "enum".to_string(),
+            Tag::Enum as u32,
+        )));
+        lex.reserve(Word::Word(WordBase::new(
+            "union".to_string(),
+            Tag::Union as u32,
+        )));
+        lex.reserve(Word::Word(WordBase::new(
+            "match".to_string(),
+            Tag::Match as u32,
+        )));
+        lex.reserve(Word::Word(WordBase::new(
+            "assert".to_string(),
+            Tag::Assert as u32,
+        )));
+        lex.reserve(Word::Word(WordBase::new(
+            "module".to_string(),
+            Tag::Module as u32,
+        )));
+        lex.reserve(Word::Word(WordBase::new(
+            "import".to_string(),
+            Tag::Import as u32,
+        )));
+        lex.reserve(Word::Word(WordBase::new(
+            "type".to_string(),
+            Tag::Type as u32,
+        )));
+        lex.reserve(Word::Word(WordBase::new(
+            "var".to_string(),
+            Tag::Var as u32,
+        )));
+        lex.reserve(Word::Word(WordBase::new(
+            "goto".to_string(),
+            Tag::Goto as u32,
+        )));
+        lex.reserve(Word::Word(WordBase::new(
+            "print".to_string(),
+            Tag::Print as u32,
+        )));
+        lex.reserve(Word::Word(WordBase::new(
+            "read".to_string(),
+            Tag::Read as u32,
+        )));
+        lex.reserve(Word::Word(WordBase::new(
+            "requires".to_string(),
+            Tag::Requires as u32,
+        )));
+        lex.reserve(Word::Word(WordBase::new(
+            "ensures".to_string(),
+            Tag::Ensures as u32,
+        )));
+        lex.reserve(Word::Word(WordBase::new(
+            "invariant".to_string(),
+            Tag::Invariant as u32,
+        )));
+
         lex.reserve(Word::Word(word_true()));
         lex.reserve(Word::Word(word_false()));
 
         lex.reserve(Word::Type(type_int()));
+        lex.reserve(Word::Type(type_i8()));
+        lex.reserve(Word::Type(type_i16()));
+        lex.reserve(Word::Type(type_i64()));
+        lex.reserve(Word::Type(type_u8()));
+        lex.reserve(Word::Type(type_u16()));
+        lex.reserve(Word::Type(type_u32()));
+        lex.reserve(Word::Type(type_u64()));
+        lex.words.insert("i32".to_string(), Word::Type(type_int()));
         lex.reserve(Word::Type(type_char()));
         lex.reserve(Word::Type(type_bool()));
         lex.reserve(Word::Type(type_float()));
+        lex.reserve(Word::Type(type_bigint()));
 
         lex
     }
@@ -319,6 +801,7 @@ impl Lexer {
             Ok(x) => {
                 if x != 0 {
                     self.peek = buffer[0] as char;
+                    self.col += 1;
                 } else {
                     self.eof = true;
                 }
@@ -337,11 +820,13 @@ impl Lexer {
     }
 
     pub fn scan(&mut self) -> Token {
+        let after_dot = std::mem::replace(&mut self.after_dot, false);
         loop {
             if self.peek == ' ' || self.peek == '\t' {
                 ()
             } else if self.peek == '\n' {
                 self.line_num = self.line_num + 1;
+                self.col = 0;
             } else {
                 break;
             }
@@ -352,6 +837,7 @@ impl Lexer {
                 return Token::Eof;
             }
         }
+        self.start = (self.line_num, self.col);
 
         match self.peek {
             '&' => {
@@ -371,28 +857,111 @@ impl Lexer {
             '=' => {
                 if self.readch('=') {
                     return Token::Word(Word::Word(word_eq()));
+                } else if self.peek == '>' {
+                    self.peek = ' ';
+                    return Token::Word(Word::Word(word_arrow()));
                 } else {
                     return Token::Token(TokenBase::new('=' as u32));
                 }
             }
+            '<' => {
+                if self.readch('=') {
+                    return Token::Word(Word::Word(word_le()));
+                } else if self.peek == '<' {
+                    self.peek = ' ';
+                    return Token::Word(Word::Word(word_shl()));
+                } else {
+                    return Token::Token(TokenBase::new('<' as u32));
+                }
+            }
+            '>' => {
+                if self.readch('=') {
+                    return Token::Word(Word::Word(word_ge()));
+                } else if self.peek == '>' {
+                    self.peek = ' ';
+                    return Token::Word(Word::Word(word_shr()));
+                } else {
+                    return Token::Token(TokenBase::new('>' as u32));
+                }
+            }
+            '+' => {
+                if self.readch('=') {
+                    return Token::Word(Word::Word(word_add_assign()));
+                } else if self.peek == '+' {
+                    self.peek = ' ';
+                    return Token::Word(Word::Word(word_inc()));
+                } else {
+                    return Token::Token(TokenBase::new('+' as u32));
+                }
+            }
+            '-' => {
+                if self.readch('=') {
+                    return Token::Word(Word::Word(word_sub_assign()));
+                } else if self.peek == '-' {
+                    self.peek = ' ';
+                    return Token::Word(Word::Word(word_dec()));
+                } else {
+                    return Token::Token(TokenBase::new('-' as u32));
+                }
+            }
+            '*' => {
+                if self.readch('=') {
+                    return Token::Word(Word::Word(word_mul_assign()));
+                } else {
+                    return Token::Token(TokenBase::new('*' as u32));
+                }
+            }
+            '/' => {
+                if self.readch('=') {
+                    return Token::Word(Word::Word(word_div_assign()));
+                } else {
+                    return Token::Token(TokenBase::new('/' as u32));
+                }
+            }
+            '\'' => {
+                // Character literal such as 'a' or '\n'
+                self.read_char();
+                let mut c = self.peek;
+                if c == '\\' {
+                    self.read_char();
+                    c = match self.peek {
+                        'n' => '\n',
+                        't' => '\t',
+                        '0' => '\0',
+                        c => c,
+                    };
+                }
+                if !self.readch('\'') {
+                    println!(
+                        "Lexical error on line {}: unterminated character literal",
+                        self.line_num
+                    );
+                    std::process::exit(0);
+                }
+                return Token::Num(Num::char(c));
+            }
             _ => (),
         }
 
         // Number handling
         if self.peek.is_digit(10) {
-            let mut v: u32 = 0;
+            let mut s = String::new();
             loop {
-                v = 10 * v + self.peek.to_digit(10).unwrap();
+                s.push(self.peek);
                 self.read_char();
                 if !self.peek.is_digit(10) {
                     break;
                 }
             }
-            if self.peek != '.' {
-                return Token::Num(Num::new(v));
+            if self.peek != '.' || after_dot {
+                // Literals too large for u64 are bigint ones
+                return match s.parse() {
+                    Ok(v) => Token::Num(Num::new(v)),
+                    Err(_) => Token::BigNum(BigNum::new(s)),
+                };
             }
 
-            let mut x = v as f32;
+            let mut x: f32 = s.parse().unwrap();
             let mut d = 10 as f32;
             loop {
                 self.read_char();
@@ -435,6 +1004,7 @@ impl Lexer {
         }
 
         let tok = Token::Token(TokenBase::new(self.peek as u32));
+        self.after_dot = self.peek == '.';
         self.peek = ' ';
         tok
     }
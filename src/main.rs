@@ -1,19 +1,135 @@
+mod bigint;
 mod ir;
 mod lexer;
 mod parser;
 mod symbols;
+mod verify;
 
+use std::path::Path;
+
+use ir::ExprAble;
 use lexer::Lexer;
 use parser::Parser;
+use symbols::Symbol;
+
+// Prints where the name at line:column of the main module is declared, and where it is used
+// when references is set
+fn locate(parser: &Parser, file_name: &str, position: &str, references: bool) {
+    let (line, col) = match position.split_once(':') {
+        Some((line, col)) => match (line.parse(), col.parse()) {
+            (Ok(line), Ok(col)) => (line, col),
+            _ => return println!("Position must be line:column"),
+        },
+        None => return println!("Position must be line:column"),
+    };
+
+    let tree = parser.scopes();
+    let tree = tree.borrow();
+    let s = match tree.symbol_at(parser.name(), line, col) {
+        Some(s) => s,
+        None => return println!("No name at {}:{}", line, col),
+    };
+    let location = |scope: usize, span: &lexer::Span| {
+        let module = &tree.scopes[scope].module;
+        let path = Path::new(file_name).with_file_name(format!("{}.dbook", module));
+        format!("{}:{}:{}", path.display(), span.line, span.col)
+    };
+
+    let d = &tree.symbols[s];
+    println!("{}: {} declared", location(d.scope, &d.span), d.name);
+    if references {
+        for r in tree.references_to(s) {
+            println!("{}: {} used", location(r.scope, &r.span), d.name);
+        }
+    }
+}
+
+// Prints the variables of all modules sorted by module and position with their storage, and
+// the size of the frame
+fn dump(parser: &Parser) {
+    let tree = parser.scopes();
+    let tree = tree.borrow();
+    let mut rows = Vec::new();
+    for d in tree.symbols.iter() {
+        if let Symbol::Var(id) = &d.symbol {
+            let p = id.get_type();
+            rows.push((
+                &tree.scopes[d.scope].module,
+                d.span.line,
+                d.span.col,
+                tree.depth(d.scope),
+                &d.name,
+                p.word.lexeme.clone(),
+                p.get_width(),
+                id.offset(),
+            ));
+        }
+    }
+    rows.sort();
+
+    println!("module\tdepth\tline\tname\ttype\twidth\toffset");
+    for (module, line, _, depth, name, p, width, offset) in rows {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            module, depth, line, name, p, width, offset
+        );
+    }
+    println!("frame size: {}", parser.frame_size());
+}
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
+    let mut args = std::env::args().skip(1);
+    let mut asserts = true;
+    let mut verify = false;
+    let mut query = None;
+    let mut symbols = false;
+    let mut shadowing = false;
+    let mut files = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--no-asserts" => asserts = false,
+            "--verify" => verify = true,
+            "--symbols" => symbols = true,
+            "--warn-shadowing" => shadowing = true,
+            "--definition" | "--references" => {
+                query = Some((arg == "--references", args.next().unwrap_or_default()))
+            }
+            _ => files.push(arg),
+        }
+    }
+    if files.len() != 1 {
         println!("Lexical analyzer needs 1 argument - source file name");
+        println!("Options: --no-asserts - do not generate code for assert statements");
+        println!("         --verify - check the annotations instead of generating code");
+        println!("         --warn-shadowing - warn when a declaration hides an outer one");
+        println!("         --symbols - list the variables with their storage instead of the code");
+        println!("         --definition line:column - show the declaration of a name");
+        println!("         --references line:column - show the declaration and uses of a name");
         return ();
     }
-    let lex = Lexer::new(&(args[1]));
-    let mut parser = Parser::new(lex);
+    let lex = Lexer::new(&files[0]);
+    let mut parser = Parser::new(lex, &files[0]);
+    if !asserts {
+        parser.disable_asserts();
+    }
+    if verify {
+        parser.enable_verify();
+    }
+    if shadowing {
+        parser.warn_shadowing();
+    }
+    if let Some((references, position)) = query {
+        parser.disable_codegen();
+        parser.program();
+        locate(&parser, &files[0], &position, references);
+        return;
+    }
+    if symbols {
+        parser.disable_codegen();
+        parser.program();
+        dump(&parser);
+        return;
+    }
     parser.program();
     println!("");
 }
@@ -1,50 +1,221 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
 use std::rc::Rc;
 
 use crate::ir::*;
 use crate::lexer::*;
 use crate::symbols::*;
+use crate::verify::verify;
 
 pub struct Parser {
     // lex - lexical analyzer for this parser
     // look - lookahead token
-    // top - current or top symbol table
+    // start - line and column of the lookahead token
+    // scope - current scope in the scope tree
+    // bindings - declarations visible at this point of the module, innermost last
     // enclosing - pointer to enclosing loop
     // temp_count - number of temporary variables
     // labels - number of labels
+    // next - token scanned after the lookahead and pushed back
+    // label_table - statement labels and the gotos that refer to them
+    // asserts - whether assert statements are compiled or dropped
+    // verify - whether the annotations are verified instead of generating code
+    // generate - whether code is generated, the program is only parsed otherwise
+    // warn_shadowing - whether a declaration that hides one of an enclosing block is reported
+    // frame - size of the frame of the program, known once the main module is parsed
+    // name - name of the module being parsed
+    // modules - modules of the program, shared by the parsers of all of them
+    // tree - scopes of the program, shared by the parsers of all modules
     lex: Lexer,
     look: Token,
-    top: Option<Box<Env>>,
+    start: (u32, u32),
+    next: Option<(Token, (u32, u32))>,
+    scope: ScopeId,
+    bindings: HashMap<String, Vec<SymbolId>>,
     enclosing: bool,
-    temp_count: Rc<RefCell<u8>>,
+    temp_count: Rc<RefCell<u32>>,
     labels: Rc<RefCell<u32>>,
-    used: u32,
+    label_table: LabelTable,
+    asserts: bool,
+    verify: bool,
+    generate: bool,
+    warn_shadowing: bool,
+    frame: u32,
+    name: String,
+    modules: Rc<RefCell<Modules>>,
+    tree: Rc<RefCell<ScopeTree>>,
 }
 
 impl Parser {
     fn move_(&mut self) {
-        self.look = self.lex.scan();
+        (self.look, self.start) = match self.next.take() {
+            Some(t) => t,
+            None => self.scan(),
+        };
+    }
+
+    fn scan(&mut self) -> (Token, (u32, u32)) {
+        let t = self.lex.scan();
+        (t, self.lex.start)
     }
 
-    pub fn new(l: Lexer) -> Parser {
+    /// Parser of the main module read from file_name, the modules it imports are looked up in
+    /// the same directory
+    pub fn new(l: Lexer, file_name: &str) -> Parser {
+        let path = Path::new(file_name);
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+        let dir = path.parent().unwrap().to_path_buf();
+        let modules = Rc::new(RefCell::new(Modules::new(dir, &name)));
+        Parser::module(
+            l,
+            name,
+            modules,
+            Rc::new(RefCell::new(ScopeTree::new())),
+            Rc::new(RefCell::new(0)),
+            Rc::new(RefCell::new(0)),
+        )
+    }
+
+    // Temporaries and labels are counted across all modules so their names never clash
+    fn module(
+        l: Lexer,
+        name: String,
+        modules: Rc<RefCell<Modules>>,
+        tree: Rc<RefCell<ScopeTree>>,
+        temp_count: Rc<RefCell<u32>>,
+        labels: Rc<RefCell<u32>>,
+    ) -> Parser {
         let mut p = Parser {
             lex: l,
             look: Token::Token(TokenBase { tag: 0 }),
-            top: None,
+            start: (1, 1),
+            next: None,
+            scope: 0,
+            bindings: HashMap::new(),
             enclosing: false,
-            temp_count: Rc::new(RefCell::new(0)),
-            labels: Rc::new(RefCell::new(0)),
-            used: 0,
+            temp_count,
+            labels,
+            label_table: LabelTable::new(),
+            asserts: true,
+            verify: false,
+            generate: true,
+            warn_shadowing: false,
+            frame: 0,
+            name,
+            modules,
+            tree,
         };
         p.move_();
         p
     }
 
+    // Assertions are still parsed and checked, but no code is generated for them
+    pub fn disable_asserts(&mut self) {
+        self.asserts = false;
+    }
+
+    pub fn enable_verify(&mut self) {
+        self.verify = true;
+    }
+
+    pub fn warn_shadowing(&mut self) {
+        self.warn_shadowing = true;
+    }
+
+    pub fn disable_codegen(&mut self) {
+        self.generate = false;
+    }
+
+    /// Scopes of the program with the declarations and uses of all names, complete once the
+    /// program is parsed
+    pub fn scopes(&self) -> Rc<RefCell<ScopeTree>> {
+        self.tree.clone()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn frame_size(&self) -> u32 {
+        self.frame
+    }
+
     fn error(&self, s: &str) -> ! {
-        println!("Syntax error on line {}: {}", self.lex.line_num, s);
+        self.report(&format!("line {}", self.lex.line_num), s)
+    }
+
+    // Reports an error about the name written at span
+    fn error_at(&self, span: Span, s: &str) -> ! {
+        self.report(&format!("line {}, column {}", span.line, span.col), s)
+    }
+
+    fn report(&self, at: &str, s: &str) -> ! {
+        if self.modules.borrow().loading.len() > 1 {
+            println!("Syntax error in module {} on {}: {}", self.name, at, s);
+            std::process::exit(0);
+        }
+        println!("Syntax error on {}: {}", at, s);
         std::process::exit(0);
     }
 
+    // Suggests the visible values whose names are closest to the undeclared one, and tells about
+    // a value with a close name in a block that has already ended
+    fn undeclared(&self, w: &WordBase, span: Span) -> ! {
+        let tree = self.tree.borrow();
+        let is_value =
+            |s: SymbolId| matches!(tree.symbols[s].symbol, Symbol::Var(_) | Symbol::Const(..));
+        let distance = |name: &str| {
+            let d = edit_distance(&w.lexeme, name);
+            (d <= w.lexeme.len().max(name.len()).div_ceil(3)).then_some(d)
+        };
+        let close: Vec<(usize, &String)> = self
+            .bindings
+            .iter()
+            .filter_map(|(name, visible)| {
+                let s = *visible.last()?;
+                Some((distance(name).filter(|_| is_value(s))?, name))
+            })
+            .collect();
+        let best = close.iter().map(|(d, _)| *d).min();
+        let mut names: Vec<&str> = close
+            .iter()
+            .filter(|(d, _)| Some(*d) == best)
+            .map(|(_, name)| name.as_str())
+            .collect();
+        names.sort();
+
+        let mut s = format!("{} undeclared", w.lexeme);
+        if let Some((last, rest)) = names.split_last() {
+            if rest.is_empty() {
+                s += &format!(", did you mean {}?", last);
+            } else {
+                s += &format!(", did you mean {} or {}?", rest.join(", "), last);
+            }
+        }
+
+        let mut open = vec![self.scope];
+        while let Some(parent) = tree.scopes[*open.last().unwrap()].parent {
+            open.push(parent);
+        }
+        let ended = (0..tree.symbols.len())
+            .filter(|s| {
+                let d = &tree.symbols[*s];
+                is_value(*s) && tree.scopes[d.scope].module == self.name && !open.contains(&d.scope)
+            })
+            .filter_map(|s| Some((distance(&tree.symbols[s].name)?, s)))
+            .min_by_key(|(d, s)| (*d, std::cmp::Reverse(*s)));
+        if let Some((_, d)) = ended {
+            let d = &tree.symbols[d];
+            s += if names.is_empty() { ", the" } else { " The" };
+            s += &format!(
+                " {} declared on line {} is in a block that has ended",
+                d.name, d.span.line
+            );
+        }
+        self.error_at(span, &s)
+    }
+
     fn match_(&mut self, t: u32) {
         match self.look.get_tag() {
             Some(tag) => {
@@ -58,58 +229,549 @@ impl Parser {
         };
     }
 
+    /// Compiles a module, the code of the modules it imports comes first
     pub fn program(&mut self) {
-        let s = self.block();
-        let begin = new_label(self.labels.clone());
-        let after = new_label(self.labels.clone());
-        emit_label(begin);
-        (*s).gen(begin, after, 0);
-        emit_label(after);
+        // Imported symbols are in a scope of their own outside of the module block
+        self.scope = self.tree.borrow_mut().scope(None, &self.name);
+        self.imports();
+        let (s, scope) = self.scope();
+        self.label_table.resolve();
+        let imported = self.modules.borrow().loading.len() > 1;
+        type_check(&*s, imported.then_some(self.name.as_str()));
+        // All modules are parsed when the main one is, their variables share one frame
+        if self.modules.borrow().loading.len() == 1 {
+            self.frame = self.tree.borrow().lay_out();
+        }
+        if self.verify {
+            verify(&*s, &self.name);
+        } else if self.generate {
+            let begin = new_label(self.labels.clone());
+            let after = new_label(self.labels.clone());
+            emit_label(begin);
+            (*s).gen(begin, after, 0);
+            emit_label(after);
+        }
+
+        let mut modules = self.modules.borrow_mut();
+        modules.exports.insert(self.name.clone(), scope);
+    }
+
+    // module m; import a; import b;
+    fn imports(&mut self) {
+        if self.look.get_tag().unwrap() == Tag::Module as u32 {
+            self.match_(Tag::Module as u32);
+            let w = self.id();
+            self.match_(';' as u32);
+            if w.lexeme != self.name {
+                self.error(&format!(
+                    "module {} must be in the file {}.dbook",
+                    w.lexeme, w.lexeme
+                ));
+            }
+        }
+
+        let mut imported = Vec::new();
+        while self.look.get_tag().unwrap() == Tag::Import as u32 {
+            self.match_(Tag::Import as u32);
+            let w = self.id();
+            self.match_(';' as u32);
+            if imported.contains(&w.lexeme) {
+                self.error(&format!("module {} imported twice", w.lexeme));
+            }
+
+            let scope = self.import(&w.lexeme);
+            let exports: Vec<(String, SymbolId)> = self.tree.borrow().scopes[scope]
+                .names
+                .iter()
+                .map(|(name, s)| (name.clone(), *s))
+                .collect();
+            for (name, s) in exports {
+                if self.bindings.contains_key(&name) {
+                    self.error(&format!(
+                        "{} exported by module {} is already imported",
+                        name, w.lexeme
+                    ));
+                }
+                self.tree.borrow_mut().import(self.scope, &name, s);
+                self.bindings.insert(name, vec![s]);
+            }
+            imported.push(w.lexeme);
+        }
+    }
+
+    // Compiles the module name unless it has been compiled already and returns its exports
+    fn import(&mut self, name: &str) -> ScopeId {
+        let path = {
+            let modules = self.modules.borrow();
+            if let Some(scope) = modules.exports.get(name) {
+                return *scope;
+            }
+            if modules.loading.iter().any(|m| m == name) {
+                let mut cycle = modules.loading.clone();
+                cycle.push(name.to_string());
+                self.error(&format!("import cycle {}", cycle.join(" -> ")));
+            }
+            modules.dir.join(format!("{}.dbook", name))
+        };
+        if !path.is_file() {
+            self.error(&format!("module {} not found", name));
+        }
+
+        let mut parser = Parser::module(
+            Lexer::new(path.to_str().unwrap()),
+            name.to_string(),
+            self.modules.clone(),
+            self.tree.clone(),
+            self.temp_count.clone(),
+            self.labels.clone(),
+        );
+        parser.asserts = self.asserts;
+        parser.verify = self.verify;
+        parser.generate = self.generate;
+        parser.warn_shadowing = self.warn_shadowing;
+
+        self.modules.borrow_mut().loading.push(name.to_string());
+        parser.program();
+        self.modules.borrow_mut().loading.pop();
+
+        self.modules.borrow().exports[name]
     }
 
     fn block(&mut self) -> Box<dyn StmtAble> {
+        self.scope().0
+    }
+
+    // Parses a block and returns its statements together with its scope
+    fn scope(&mut self) -> (Box<dyn StmtAble>, ScopeId) {
         self.match_('{' as u32);
-        self.top = Some(Box::new(Env::new(self.top.take())));
-        self.decls();
-        let s = self.stmts();
+        // The block of a module is the only one right inside the scope of imported symbols
+        let entry = self.tree.borrow().scopes[self.scope].parent.is_none();
+        self.enter();
+        let inits = self.decls();
+        let (requires, ensures) = self.contract();
+        let mut s = self.stmts();
+        if !requires.is_empty() || !ensures.is_empty() {
+            s = Box::new(Contract::new(requires, ensures, s, entry));
+        }
         self.match_('}' as u32);
-        self.top = self.top.take().unwrap().prev;
+        let scope = self.scope;
+        self.leave();
+        (Box::new(Seq::new(inits, s, self.labels.clone())), scope)
+    }
+
+    fn enter(&mut self) {
+        self.scope = self.tree.borrow_mut().scope(Some(self.scope), &self.name);
+        self.label_table.enter();
+    }
+
+    // The names declared in the scope go out of sight, their declarations stay in the tree
+    fn leave(&mut self) {
+        let tree = self.tree.borrow();
+        let scope = &tree.scopes[self.scope];
+        for name in scope.names.keys() {
+            self.bindings.get_mut(name).unwrap().pop();
+        }
+        self.scope = scope.parent.unwrap();
+        drop(tree);
+        self.label_table.leave();
+    }
+
+    // Declares a name in the current scope, span is where the name is written
+    fn declare(&mut self, w: &WordBase, symbol: Symbol, span: Span) -> SymbolId {
+        let outer = self.bindings.get(&w.lexeme).and_then(|v| v.last().copied());
+        if let Some(outer) = outer {
+            let tree = self.tree.borrow();
+            let d = &tree.symbols[outer];
+            let module = &tree.scopes[d.scope].module;
+            if d.scope == self.scope {
+                let at = format!("line {}, column {}", d.span.line, d.span.col);
+                drop(tree);
+                self.error_at(
+                    span,
+                    &format!("{} redeclared, it is declared on {}", w.lexeme, at),
+                );
+            } else if self.warn_shadowing && *module != self.name {
+                let s = format!(
+                    "{} shadows the one imported from module {}",
+                    w.lexeme, module
+                );
+                warning(&s, span.line);
+            } else if self.warn_shadowing {
+                let s = format!(
+                    "{} shadows the one declared on line {}",
+                    w.lexeme, d.span.line
+                );
+                warning(&s, span.line);
+            }
+        }
+        let s = self.tree.borrow_mut().declare(self.scope, w, symbol, span);
+        self.bindings.entry(w.lexeme.clone()).or_default().push(s);
         s
     }
 
-    fn decls(&mut self) {
-        while self.look.get_tag().unwrap() == Tag::Basic as u32 {
-            let p = self.type_();
-            let tok = self.look.clone();
-            self.match_(Tag::Id as u32);
+    // Declares a variable of type p, it is placed in the frame once the program is parsed
+    fn declare_var(&mut self, w: WordBase, p: &TypeBase, span: Span) -> Id {
+        let s = self.tree.borrow().symbols.len();
+        let id = Id::new(w.clone(), p.clone(), s);
+        self.label_table.declare(&w);
+        self.declare(&w, Symbol::Var(id.clone()), span);
+        id
+    }
+
+    // Symbol the name stands for at this point of the module
+    fn lookup(&self, w: &WordBase) -> Option<(SymbolId, Symbol)> {
+        let s = *self.bindings.get(&w.lexeme)?.last()?;
+        Some((s, self.tree.borrow().symbols[s].symbol.clone()))
+    }
+
+    // Looks the name up and records the use at span
+    fn resolve(&mut self, w: &WordBase, span: Span) -> Option<Symbol> {
+        let (s, symbol) = self.lookup(w)?;
+        self.tree.borrow_mut().refer(s, self.scope, span);
+        Some(symbol)
+    }
+
+    // Source range of the lookahead token
+    fn span(&self) -> Span {
+        Span {
+            line: self.start.0,
+            col: self.start.1,
+            len: self.look.to_string().len() as u32,
+        }
+    }
+
+    // requires(x); ensures(x); after the declarations of a block, they are checked after the
+    // initializations
+    fn contract(&mut self) -> (Annotations, Annotations) {
+        let mut requires = Vec::new();
+        let mut ensures = Vec::new();
+        loop {
+            let tag = self.look.get_tag().unwrap();
+            if tag != Tag::Requires as u32 && tag != Tag::Ensures as u32 {
+                break;
+            }
+            let line = self.lex.line_num;
+            self.move_();
+            self.match_('(' as u32);
+            let x = self.assign();
+            self.match_(')' as u32);
             self.match_(';' as u32);
-            let w = match tok.clone() {
-                Token::Word(word) => match word {
-                    Word::Word(base) => base,
-                    _ => panic!("decls"),
-                },
-                _ => panic!("decls"),
-            };
-            let id = Id::new(w.clone(), p.clone(), self.used);
-            (*self.top.as_mut().unwrap()).put(w, id);
-            self.used += p.get_width();
+            if tag == Tag::Requires as u32 {
+                requires.push((x, line));
+            } else {
+                ensures.push((x, line));
+            }
         }
+        (requires, ensures)
     }
 
-    fn type_(&mut self) -> TypeBase {
-        let p = match self.look.clone() {
-            Token::Word(word) => match word {
-                Word::Type(t) => t,
-                Word::Word(word_base) => {
-                    self.error(&word_base.lexeme);
+    // Returns the initializations of the declared variables
+    fn decls(&mut self) -> Box<dyn StmtAble> {
+        let mut inits: Box<dyn StmtAble> = Box::new(Null {});
+        loop {
+            if self.look.get_tag().unwrap() == Tag::Enum as u32 {
+                self.enum_();
+            } else if self.look.get_tag().unwrap() == Tag::Type as u32 {
+                self.alias();
+            } else if self.look.get_tag().unwrap() == Tag::Var as u32 {
+                let s = self.var();
+                inits = Box::new(Seq::new(inits, s, self.labels.clone()));
+            } else if self.look.get_tag().unwrap() == Tag::Union as u32 {
+                self.union_();
+            } else if self.type_name().is_some() || self.tuple_type_ahead() {
+                let p = self.type_();
+                let span = self.span();
+                let w = self.id();
+                self.match_(';' as u32);
+                self.declare_var(w, &p, span);
+            } else {
+                break;
+            }
+        }
+        inits
+    }
+
+    // type Meters = float; the name stands for the target type itself, so an alias and its
+    // target are the same type everywhere
+    fn alias(&mut self) {
+        self.match_(Tag::Type as u32);
+        let span = self.span();
+        let w = self.id();
+        self.match_('=' as u32);
+        let p = self.type_();
+        self.match_(';' as u32);
+        self.declare(&w, Symbol::Type(p), span);
+    }
+
+    // var x = expr; declares x with the type of expr
+    fn var(&mut self) -> Box<dyn StmtAble> {
+        let line = self.lex.line_num;
+        self.match_(Tag::Var as u32);
+        let span = self.span();
+        let w = self.id();
+        if self.look.get_tag().unwrap() != '=' as u32 {
+            self.error(&format!("var {} needs an initializer", w.lexeme));
+        }
+        let tok = self.look.clone();
+        self.move_();
+        let x = self.assign();
+        self.match_(';' as u32);
+
+        let p = (*x).get_type().clone();
+        if p.is_union() {
+            self.error(&format!(
+                "cannot infer the type of {} from its initializer",
+                w.lexeme
+            ));
+        }
+        let id = self.declare_var(w, &p, span);
+
+        if p.is_tuple() {
+            let targets = self.expand(Box::new(id));
+            let values = self.expand(x);
+            let set = MultiSet::new(tok, targets, values, line, self.temp_count.clone());
+            return Box::new(ExprStmt::new(Box::new(set), line));
+        }
+        let set = Set::new(tok, Box::new(id), x, line, self.temp_count.clone());
+        Box::new(ExprStmt::new(Box::new(set), line))
+    }
+
+    // enum E { A, B = 4, C }
+    fn enum_(&mut self) {
+        self.match_(Tag::Enum as u32);
+        let span = self.span();
+        let w = self.id();
+        let p = type_enum(&w.lexeme);
+        self.declare(&w, Symbol::Type(p.clone()), span);
+
+        self.match_('{' as u32);
+        let mut value = 0;
+        loop {
+            let span = self.span();
+            let c = self.id();
+            if self.look.get_tag().unwrap() == '=' as u32 {
+                self.move_();
+                value = match self.look {
+                    Token::Num(ref n) if n.value <= type_int().max_value() => n.value as u32,
+                    _ => self.error(&self.look.to_string()),
+                };
+                self.move_();
+            }
+            self.declare(&c, Symbol::Const(p.clone(), value), span);
+            value += 1;
+
+            if self.look.get_tag().unwrap() != ',' as u32 {
+                break;
+            }
+            self.move_();
+        }
+        self.match_('}' as u32);
+        if self.look.get_tag().unwrap() == ';' as u32 {
+            self.move_();
+        }
+    }
+
+    // union U { A(int, float), B, C(bool) }
+    fn union_(&mut self) {
+        self.match_(Tag::Union as u32);
+        let span = self.span();
+        let w = self.id();
+
+        self.match_('{' as u32);
+        let mut names = Vec::new();
+        let mut variants: Vec<Variant> = Vec::new();
+        loop {
+            let v_span = self.span();
+            let v = self.id();
+            if variants.iter().any(|x| x.name == v.lexeme) {
+                self.error(&format!("variant {} declared twice", v.lexeme));
+            }
+            let mut fields = Vec::new();
+            if self.look.get_tag().unwrap() == '(' as u32 {
+                self.move_();
+                loop {
+                    let p = self.type_();
+                    if p.is_tuple() {
+                        self.error(&format!("{} cannot be a field of a union", p.word.lexeme));
+                    }
+                    fields.push(p);
+                    if self.look.get_tag().unwrap() != ',' as u32 {
+                        break;
+                    }
+                    self.move_();
                 }
+                self.match_(')' as u32);
+            }
+            variants.push(Variant {
+                name: v.lexeme.clone(),
+                fields,
+            });
+            names.push((v, v_span));
+
+            if self.look.get_tag().unwrap() != ',' as u32 {
+                break;
+            }
+            self.move_();
+        }
+        self.match_('}' as u32);
+        if self.look.get_tag().unwrap() == ';' as u32 {
+            self.move_();
+        }
+
+        let p = type_union(&w.lexeme, variants);
+        self.declare(&w, Symbol::Type(p.clone()), span);
+        for (i, (v, v_span)) in names.into_iter().enumerate() {
+            self.declare(&v, Symbol::Variant(p.clone(), i), v_span);
+        }
+    }
+
+    // Looks up the lookahead identifier as a variant of the union p and returns its index
+    fn variant_of(&mut self, p: &TypeBase) -> usize {
+        let span = self.span();
+        let w = self.id();
+        match self.resolve(&w, span) {
+            Some(Symbol::Variant(q, v)) if q == *p => v,
+            _ => self.error(&format!(
+                "{} is not a variant of {}",
+                w.lexeme, p.word.lexeme
+            )),
+        }
+    }
+
+    // Matches an identifier and returns its word
+    fn id(&mut self) -> WordBase {
+        let w = match &self.look {
+            Token::Word(Word::Word(base)) => base.clone(),
+            _ => self.error(&self.look.to_string()),
+        };
+        self.match_(Tag::Id as u32);
+        w
+    }
+
+    // Returns the type named by the lookahead token, a basic type or a declared one
+    fn type_name(&self) -> Option<TypeBase> {
+        match &self.look {
+            Token::Word(Word::Type(t)) => Some(t.clone()),
+            Token::Word(Word::Word(w)) => match self.lookup(w) {
+                Some((_, Symbol::Type(t))) => Some(t),
+                _ => None,
             },
-            _ => panic!("Expected type"),
+            _ => None,
+        }
+    }
+
+    fn type_(&mut self) -> TypeBase {
+        let mut p = if self.look.get_tag().unwrap() == '(' as u32 {
+            self.tuple_type()
+        } else {
+            let p = match self.type_name() {
+                Some(p) => p,
+                None => self.error(&self.look.to_string()),
+            };
+            if let Token::Word(Word::Word(w)) = self.look.clone() {
+                self.resolve(&w, self.span());
+            }
+            self.move_();
+            p
         };
-        self.match_(Tag::Basic as u32);
+        while self.look.get_tag().unwrap() == '*' as u32 {
+            self.move_();
+            p = type_pointer(&p);
+        }
         p
     }
 
+    // (int, float), a tuple has at least two elements and they are not tuples or unions
+    fn tuple_type(&mut self) -> TypeBase {
+        self.match_('(' as u32);
+        let mut elements = vec![self.type_()];
+        while self.look.get_tag().unwrap() == ',' as u32 {
+            self.move_();
+            elements.push(self.type_());
+        }
+        self.match_(')' as u32);
+
+        if elements.len() < 2 {
+            self.error("a tuple has at least two elements");
+        }
+        if let Some(p) = elements.iter().find(|p| p.is_aggregate()) {
+            self.error(&format!(
+                "{} cannot be an element of a tuple",
+                p.word.lexeme
+            ));
+        }
+        type_tuple(elements)
+    }
+
+    // Tells the ( of a tuple type from the one of an expression by the token after it
+    fn tuple_type_ahead(&mut self) -> bool {
+        if self.look.get_tag().unwrap() != '(' as u32 {
+            return false;
+        }
+        if self.next.is_none() {
+            self.next = Some(self.scan());
+        }
+        match &self.next {
+            Some((Token::Word(Word::Type(_)), _)) => true,
+            Some((Token::Word(Word::Word(w)), _)) => {
+                matches!(self.lookup(w), Some((_, Symbol::Type(_))))
+            }
+            _ => false,
+        }
+    }
+
+    // A tuple literal or a tuple variable stands for its elements in a multiple assignment
+    fn expand(&mut self, mut x: Box<dyn ExprAble>) -> Vec<Box<dyn ExprAble>> {
+        if let Some(xs) = (*x).elements() {
+            return xs;
+        }
+        let p = (*x).get_type().clone();
+        let elements = match p.elements() {
+            Some(elements) => elements,
+            None => return vec![x],
+        };
+        if !(*x).is_lvalue() {
+            self.error("a tuple value can only be assigned from a literal or a variable");
+        }
+        (0..elements.len())
+            .map(|i| {
+                Box::new(Access::new(
+                    (*x).gen(),
+                    p.element_offset(i),
+                    elements[i].clone(),
+                    self.temp_count.clone(),
+                )) as Box<dyn ExprAble>
+            })
+            .collect()
+    }
+
+    // a, b = b, a;
+    fn multi_set(&mut self, x: Box<dyn ExprAble>) -> Box<dyn StmtAble> {
+        let line = self.lex.line_num;
+        let mut targets = self.expand(x);
+        while self.look.get_tag().unwrap() == ',' as u32 {
+            self.move_();
+            let x = self.cond();
+            targets.extend(self.expand(x));
+        }
+        let tok = self.look.clone();
+        self.match_('=' as u32);
+
+        let mut values = Vec::new();
+        loop {
+            let y = self.assign();
+            values.extend(self.expand(y));
+            if self.look.get_tag().unwrap() != ',' as u32 {
+                break;
+            }
+            self.move_();
+        }
+        self.match_(';' as u32);
+
+        let set = MultiSet::new(tok, targets, values, line, self.temp_count.clone());
+        Box::new(ExprStmt::new(Box::new(set), line))
+    }
+
     fn stmts(&mut self) -> Box<dyn StmtAble> {
         if self.look.get_tag().unwrap() == '}' as u32 {
             Box::new(Null {})
@@ -125,65 +787,291 @@ impl Parser {
             self.move_();
             Box::new(Null {})
         } else if tag == Tag::If as u32 {
+            let line = self.lex.line_num;
             self.match_(Tag::If as u32);
             self.match_('(' as u32);
-            let x = self.bool_();
+            let x = self.assign();
             self.match_(')' as u32);
 
             let s1 = self.stmt();
             if self.look.get_tag().unwrap() != Tag::Else as u32 {
-                return Box::new(If::new(x, s1, self.lex.line_num, self.labels.clone()));
+                return Box::new(If::new(x, s1, line, self.labels.clone()));
             }
             self.match_(Tag::Else as u32);
             let s2 = self.stmt();
-            Box::new(Else::new(x, s1, s2, self.lex.line_num, self.labels.clone()))
+            Box::new(Else::new(x, s1, s2, line, self.labels.clone()))
         } else if tag == Tag::While as u32 {
             self.enclosing = true;
             let mut while_node = Box::new(While::new(self.lex.line_num, self.labels.clone()));
             self.match_(Tag::While as u32);
             self.match_('(' as u32);
-            let x = self.bool_();
+            let x = self.assign();
             self.match_(')' as u32);
+            while self.look.get_tag().unwrap() == Tag::Invariant as u32 {
+                let line = self.lex.line_num;
+                self.move_();
+                self.match_('(' as u32);
+                let i = self.assign();
+                self.match_(')' as u32);
+                while_node.invariant(i, line);
+            }
             let s = self.stmt();
             (*while_node).init(x, s);
             while_node
         } else if tag == Tag::Break as u32 {
+            let line = self.lex.line_num;
             self.match_(Tag::Break as u32);
             self.match_(';' as u32);
 
             if !self.enclosing {
                 panic!("unenclosed break");
             }
-            Box::new(Break {})
+            Box::new(Break { line })
+        } else if tag == Tag::Print as u32 {
+            let line = self.lex.line_num;
+            self.match_(Tag::Print as u32);
+            self.match_('(' as u32);
+            let mut xs = Vec::new();
+            if self.look.get_tag().unwrap() != ')' as u32 {
+                xs.push(self.assign());
+                while self.look.get_tag().unwrap() == ',' as u32 {
+                    self.move_();
+                    xs.push(self.assign());
+                }
+            }
+            self.match_(')' as u32);
+            self.match_(';' as u32);
+            Box::new(Print::new(xs, line))
+        } else if tag == Tag::Read as u32 {
+            let line = self.lex.line_num;
+            self.match_(Tag::Read as u32);
+            self.match_('(' as u32);
+            let x = self.assign();
+            self.match_(')' as u32);
+            self.match_(';' as u32);
+            Box::new(Read::new(x, line, self.temp_count.clone()))
+        } else if tag == Tag::Match as u32 {
+            self.cases()
+        } else if tag == Tag::Assert as u32 {
+            let line = self.lex.line_num;
+            self.match_(Tag::Assert as u32);
+            self.match_('(' as u32);
+            let x = self.assign();
+            self.match_(')' as u32);
+            self.match_(';' as u32);
+            let s = Assert::new(x, line);
+            if !self.asserts {
+                return Box::new(Null {});
+            }
+            Box::new(s)
+        } else if tag == Tag::Goto as u32 {
+            let line = self.lex.line_num;
+            self.match_(Tag::Goto as u32);
+            let w = self.id();
+            self.match_(';' as u32);
+            Box::new(Goto::new(self.label_table.goto(&w, line), line))
         } else if tag == '{' as u32 {
             self.block()
         } else {
-            self.assign()
+            // An identifier followed by a colon is a label, anything else starts an expression
+            if let (true, Token::Word(Word::Word(w))) = (tag == Tag::Id as u32, &self.look) {
+                let w = w.clone();
+                let t = self.scan();
+                if t.0.get_tag() == Some(':' as u32) {
+                    let line = self.lex.line_num;
+                    self.move_();
+                    let label = new_label(self.labels.clone());
+                    self.label_table.define(&w, label, line);
+                    return Box::new(Labeled::new(label, self.stmt()));
+                }
+                self.next = Some(t);
+            }
+            let line = self.lex.line_num;
+            let x = self.assign();
+            if self.look.get_tag().unwrap() == ',' as u32 {
+                return self.multi_set(x);
+            }
+            self.match_(';' as u32);
+            Box::new(ExprStmt::new(x, line))
         }
     }
 
-    fn assign(&mut self) -> Box<dyn StmtAble> {
-        let stmt: Box<dyn StmtAble>;
-        let t = self.look.clone();
+    // match (u) { A(x, y) => stmt B => stmt C(_) => stmt }
+    fn cases(&mut self) -> Box<dyn StmtAble> {
+        let line = self.lex.line_num;
+        self.match_(Tag::Match as u32);
+        self.match_('(' as u32);
+        let x = self.assign();
+        self.match_(')' as u32);
+        self.match_('{' as u32);
 
-        self.match_(Tag::Id as u32);
+        // The type checker reports a value that is not a union, the arms are parsed against the
+        // union of the first variant then
+        let mut p = (*x).get_type().clone();
+        if p.variants().is_none() {
+            if let Token::Word(Word::Word(w)) = &self.look {
+                if let Some((_, Symbol::Variant(q, _))) = self.lookup(w) {
+                    p = q;
+                }
+            }
+        }
+        let variants = p.variants().cloned().unwrap_or_default();
+        let mut arms: Vec<Arm> = Vec::new();
+        while self.look.get_tag().unwrap() != '}' as u32 {
+            let v = self.variant_of(&p);
+            let variant = &variants[v];
+            if arms.iter().any(|arm| arm.tag as usize == v) {
+                self.error(&format!("variant {} matched twice", variant.name));
+            }
 
-        let w = match t.clone() {
-            Token::Word(word) => match word {
-                Word::Word(base) => base,
-                _ => unreachable!(),
-            },
-            _ => unreachable!(),
+            // The bound variables live in a block of their own around the arm
+            self.enter();
+            let mut ids = Vec::new();
+            if self.look.get_tag().unwrap() == '(' as u32 {
+                self.move_();
+                loop {
+                    if ids.len() == variant.fields.len() {
+                        break;
+                    }
+                    if self.look.get_tag().unwrap() == '_' as u32 {
+                        self.move_();
+                        ids.push(None);
+                    } else {
+                        let span = self.span();
+                        let w = self.id();
+                        let f = variant.fields[ids.len()].clone();
+                        let id = self.declare_var(w, &f, span);
+                        ids.push(Some(id));
+                    }
+                    if self.look.get_tag().unwrap() != ',' as u32 {
+                        break;
+                    }
+                    self.move_();
+                }
+                self.match_(')' as u32);
+            }
+            if ids.len() != variant.fields.len() {
+                self.error(&format!(
+                    "variant {} carries {} values",
+                    variant.name,
+                    variant.fields.len()
+                ));
+            }
+            self.match_(Tag::Arrow as u32);
+            let s = self.stmt();
+            self.leave();
+
+            arms.push(Arm {
+                tag: v as u32,
+                ids,
+                stmt: s,
+            });
+        }
+        self.match_('}' as u32);
+
+        Box::new(Match::new(
+            x,
+            arms,
+            line,
+            self.temp_count.clone(),
+            self.labels.clone(),
+        ))
+    }
+
+    // Right side of an assignment to a union variable, a variant constructor V(x, ...)
+    fn variant(&mut self, x: Box<dyn ExprAble>) -> Box<dyn ExprAble> {
+        let line = self.lex.line_num;
+        let v = self.variant_of(&(*x).get_type().clone());
+        let mut xs = Vec::new();
+        if self.look.get_tag().unwrap() == '(' as u32 {
+            self.move_();
+            if self.look.get_tag().unwrap() != ')' as u32 {
+                xs.push(self.assign());
+                while self.look.get_tag().unwrap() == ',' as u32 {
+                    self.move_();
+                    xs.push(self.assign());
+                }
+            }
+            self.match_(')' as u32);
+        }
+        Box::new(SetVariant::new(x, v, xs, line, self.temp_count.clone()))
+    }
+
+    fn assign(&mut self) -> Box<dyn ExprAble> {
+        let x = self.cond();
+        let tag = self.look.get_tag().unwrap();
+        let op = if tag == Tag::AddAssign as u32 {
+            '+'
+        } else if tag == Tag::SubAssign as u32 {
+            '-'
+        } else if tag == Tag::MulAssign as u32 {
+            '*'
+        } else if tag == Tag::DivAssign as u32 {
+            '/'
+        } else if tag == '=' as u32 {
+            '='
+        } else {
+            return x;
         };
-        let id = (*self.top.as_ref().unwrap()).get(&w);
-        match id {
-            None => panic!("Undeclared"), // TODO: add temporary to_string
-            _ => {}
+
+        // A side that cannot be assigned is left to the type checker to report, see Set
+        let tok = self.look.clone();
+        self.move_();
+        if op == '=' && (*x).is_lvalue() && (*x).get_type().is_union() {
+            return self.variant(x);
+        }
+        if op == '=' && (*x).is_lvalue() && (*x).get_type().is_tuple() {
+            let line = self.lex.line_num;
+            let y = self.assign();
+            let targets = self.expand(x);
+            let values = self.expand(y);
+            return Box::new(MultiSet::new(
+                tok,
+                targets,
+                values,
+                line,
+                self.temp_count.clone(),
+            ));
         }
+        let y = self.assign();
+        if op == '=' {
+            return Box::new(Set::new(
+                tok,
+                x,
+                y,
+                self.lex.line_num,
+                self.temp_count.clone(),
+            ));
+        }
+        Box::new(SetOp::new(
+            Token::Token(TokenBase { tag: op as u32 }),
+            x,
+            y,
+            self.lex.line_num,
+            self.temp_count.clone(),
+        ))
+    }
 
-        self.match_('=' as u32);
-        stmt = Box::new(Set::new(Box::new(id.unwrap()), self.bool_()));
-        stmt
+    fn cond(&mut self) -> Box<dyn ExprAble> {
+        let x = self.bool_();
+        if self.look.get_tag().unwrap() != '?' as u32 {
+            return x;
+        }
+        let tok = self.look.clone();
+        let line = self.lex.line_num;
+        self.move_();
+        let x1 = self.assign();
+        self.match_(':' as u32);
+        let x2 = self.cond();
+        Box::new(Cond::new(
+            tok,
+            x,
+            x1,
+            x2,
+            line,
+            self.temp_count.clone(),
+            self.labels.clone(),
+        ))
     }
 
     fn bool_(&mut self) -> Box<dyn ExprAble> {
@@ -191,10 +1079,12 @@ impl Parser {
         while self.look.get_tag().unwrap() == Tag::Or as u32 {
             let tok = self.look.clone();
             self.move_();
+            let y = self.join();
             x = Box::new(Or::new(
                 tok,
                 x,
-                self.join(),
+                y,
+                self.lex.line_num,
                 self.temp_count.clone(),
                 self.labels.clone(),
             ));
@@ -203,14 +1093,16 @@ impl Parser {
     }
 
     fn join(&mut self) -> Box<dyn ExprAble> {
-        let mut x = self.equality();
+        let mut x = self.bitor();
         while self.look.get_tag().unwrap() == Tag::And as u32 {
             let tok = self.look.clone();
             self.move_();
+            let y = self.bitor();
             x = Box::new(And::new(
                 tok,
                 x,
-                self.equality(),
+                y,
+                self.lex.line_num,
                 self.temp_count.clone(),
                 self.labels.clone(),
             ));
@@ -218,6 +1110,54 @@ impl Parser {
         x
     }
 
+    fn bitor(&mut self) -> Box<dyn ExprAble> {
+        let mut x = self.bitxor();
+        while self.look.get_tag().unwrap() == '|' as u32 {
+            let tok = self.look.clone();
+            self.move_();
+            x = Box::new(IntArith::new(
+                tok,
+                x,
+                self.bitxor(),
+                self.lex.line_num,
+                self.temp_count.clone(),
+            ));
+        }
+        x
+    }
+
+    fn bitxor(&mut self) -> Box<dyn ExprAble> {
+        let mut x = self.bitand();
+        while self.look.get_tag().unwrap() == '^' as u32 {
+            let tok = self.look.clone();
+            self.move_();
+            x = Box::new(IntArith::new(
+                tok,
+                x,
+                self.bitand(),
+                self.lex.line_num,
+                self.temp_count.clone(),
+            ));
+        }
+        x
+    }
+
+    fn bitand(&mut self) -> Box<dyn ExprAble> {
+        let mut x = self.equality();
+        while self.look.get_tag().unwrap() == '&' as u32 {
+            let tok = self.look.clone();
+            self.move_();
+            x = Box::new(IntArith::new(
+                tok,
+                x,
+                self.equality(),
+                self.lex.line_num,
+                self.temp_count.clone(),
+            ));
+        }
+        x
+    }
+
     fn equality(&mut self) -> Box<dyn ExprAble> {
         let mut x = self.rel();
         while self.look.get_tag().unwrap() == Tag::Eq_ as u32
@@ -229,6 +1169,7 @@ impl Parser {
                 tok,
                 x,
                 self.rel(),
+                self.lex.line_num,
                 self.temp_count.clone(),
                 self.labels.clone(),
             ));
@@ -237,7 +1178,7 @@ impl Parser {
     }
 
     fn rel(&mut self) -> Box<dyn ExprAble> {
-        let x = self.expr();
+        let x = self.shift();
         let tag = self.look.get_tag().unwrap();
 
         if tag == '<' as u32 || tag == Tag::Le as u32 || tag == Tag::Ge as u32 || tag == '>' as u32
@@ -247,7 +1188,8 @@ impl Parser {
             Box::new(Rel::new(
                 tok,
                 x,
-                self.expr(),
+                self.shift(),
+                self.lex.line_num,
                 self.temp_count.clone(),
                 self.labels.clone(),
             ))
@@ -256,17 +1198,17 @@ impl Parser {
         }
     }
 
-    fn expr(&mut self) -> Box<dyn ExprAble> {
-        let mut x = self.term();
-        while self.look.get_tag().unwrap() == '+' as u32
-            || self.look.get_tag().unwrap() == '-' as u32
+    fn shift(&mut self) -> Box<dyn ExprAble> {
+        let mut x = self.expr();
+        while self.look.get_tag().unwrap() == Tag::Shl as u32
+            || self.look.get_tag().unwrap() == Tag::Shr as u32
         {
             let tok = self.look.clone();
             self.move_();
-            x = Box::new(Arith::new(
+            x = Box::new(IntArith::new(
                 tok,
                 x,
-                self.term(),
+                self.expr(),
                 self.lex.line_num,
                 self.temp_count.clone(),
             ));
@@ -274,17 +1216,17 @@ impl Parser {
         x
     }
 
-    fn term(&mut self) -> Box<dyn ExprAble> {
-        let mut x = self.unary();
-        while self.look.get_tag().unwrap() == '*' as u32
-            || self.look.get_tag().unwrap() == '/' as u32
+    fn expr(&mut self) -> Box<dyn ExprAble> {
+        let mut x = self.term();
+        while self.look.get_tag().unwrap() == '+' as u32
+            || self.look.get_tag().unwrap() == '-' as u32
         {
             let tok = self.look.clone();
             self.move_();
             x = Box::new(Arith::new(
                 tok,
                 x,
-                self.unary(),
+                self.term(),
                 self.lex.line_num,
                 self.temp_count.clone(),
             ));
@@ -292,12 +1234,44 @@ impl Parser {
         x
     }
 
+    fn term(&mut self) -> Box<dyn ExprAble> {
+        let mut x = self.unary();
+        loop {
+            let tag = self.look.get_tag().unwrap();
+            if tag == '*' as u32 || tag == '/' as u32 {
+                let tok = self.look.clone();
+                self.move_();
+                x = Box::new(Arith::new(
+                    tok,
+                    x,
+                    self.unary(),
+                    self.lex.line_num,
+                    self.temp_count.clone(),
+                ));
+            } else if tag == '%' as u32 {
+                let tok = self.look.clone();
+                self.move_();
+                x = Box::new(IntArith::new(
+                    tok,
+                    x,
+                    self.unary(),
+                    self.lex.line_num,
+                    self.temp_count.clone(),
+                ));
+            } else {
+                break;
+            }
+        }
+        x
+    }
+
     fn unary(&mut self) -> Box<dyn ExprAble> {
         if self.look.get_tag().unwrap() == '-' as u32 {
             self.move_();
             Box::new(Unary::new(
                 Token::Word(Word::Word(word_minus())),
                 self.unary(),
+                self.lex.line_num,
                 self.temp_count.clone(),
             ))
         } else if self.look.get_tag().unwrap() == '!' as u32 {
@@ -306,26 +1280,141 @@ impl Parser {
             Box::new(Not::new(
                 tok,
                 self.unary(),
+                self.lex.line_num,
                 self.temp_count.clone(),
                 self.labels.clone(),
             ))
+        } else if self.look.get_tag().unwrap() == '~' as u32 {
+            let tok = self.look.clone();
+            self.move_();
+            Box::new(BitNot::new(
+                tok,
+                self.unary(),
+                self.lex.line_num,
+                self.temp_count.clone(),
+            ))
+        } else if self.look.get_tag().unwrap() == '&' as u32 {
+            let tok = self.look.clone();
+            self.move_();
+            Box::new(AddrOf::new(
+                tok,
+                self.factor(),
+                self.lex.line_num,
+                self.temp_count.clone(),
+            ))
+        } else if self.look.get_tag().unwrap() == '*' as u32 {
+            let tok = self.look.clone();
+            self.move_();
+            Box::new(Deref::new(
+                tok,
+                self.unary(),
+                self.lex.line_num,
+                self.temp_count.clone(),
+            ))
+        } else if self.look.get_tag().unwrap() == Tag::Inc as u32
+            || self.look.get_tag().unwrap() == Tag::Dec as u32
+        {
+            let op = self.step();
+            let x = self.unary();
+            Box::new(SetOp::new(
+                op,
+                x,
+                Box::new(Constant::new(Token::Num(Num::new(1)), type_int())),
+                self.lex.line_num,
+                self.temp_count.clone(),
+            ))
         } else {
-            self.factor()
+            self.postfix()
         }
     }
 
+    fn postfix(&mut self) -> Box<dyn ExprAble> {
+        let mut x = self.factor();
+        // t.0 is the first element of the tuple t
+        while self.look.get_tag().unwrap() == '.' as u32 {
+            self.move_();
+            let i = match &self.look {
+                Token::Num(n) => n.value as usize,
+                _ => self.error(&self.look.to_string()),
+            };
+            self.move_();
+            x = Box::new(Access::element(
+                x,
+                i,
+                self.lex.line_num,
+                self.temp_count.clone(),
+            ));
+        }
+        while self.look.get_tag().unwrap() == Tag::Inc as u32
+            || self.look.get_tag().unwrap() == Tag::Dec as u32
+        {
+            let op = self.step();
+            x = Box::new(Postfix::new(
+                op,
+                x,
+                self.lex.line_num,
+                self.temp_count.clone(),
+            ));
+        }
+        x
+    }
+
+    // Consumes ++ or -- and returns the arithmetic operator it applies
+    fn step(&mut self) -> Token {
+        let op = if self.look.get_tag().unwrap() == Tag::Inc as u32 {
+            '+'
+        } else {
+            '-'
+        };
+        self.move_();
+        Token::Token(TokenBase { tag: op as u32 })
+    }
+
     fn factor(&mut self) -> Box<dyn ExprAble> {
         match self.look.get_tag() {
             Some(tag) => {
                 if tag == '(' as u32 {
+                    let tok = self.look.clone();
+                    let line = self.lex.line_num;
                     self.move_();
-                    let x = self.bool_();
+                    if self.type_name().is_some() {
+                        let p = self.type_();
+                        self.match_(')' as u32);
+                        return Box::new(Cast::new(
+                            p,
+                            self.unary(),
+                            self.lex.line_num,
+                            self.temp_count.clone(),
+                        ));
+                    }
+                    let x = self.assign();
+                    if self.look.get_tag().unwrap() == ',' as u32 {
+                        let mut xs = vec![x];
+                        while self.look.get_tag().unwrap() == ',' as u32 {
+                            self.move_();
+                            xs.push(self.assign());
+                        }
+                        self.match_(')' as u32);
+                        return Box::new(Tuple::new(tok, xs, line));
+                    }
                     self.match_(')' as u32);
                     return x;
                 } else if tag == Tag::Num as u32 {
-                    let x = Box::new(Constant::new(self.look.clone(), type_int()));
+                    let p = match &self.look {
+                        Token::Num(n) => n.get_type(),
+                        _ => unreachable!(),
+                    };
+                    let x = Box::new(Constant::new(self.look.clone(), p));
                     self.move_();
                     return x;
+                } else if tag == Tag::BigNum as u32 {
+                    let x = Box::new(Constant::new(self.look.clone(), type_bigint()));
+                    self.move_();
+                    x
+                } else if tag == Tag::Char as u32 {
+                    let x = Box::new(Constant::new(self.look.clone(), type_char()));
+                    self.move_();
+                    x
                 } else if tag == Tag::Real as u32 {
                     let x = Box::new(Constant::new(self.look.clone(), type_float()));
                     self.move_();
@@ -340,25 +1429,27 @@ impl Parser {
                     return x;
                 } else if tag == Tag::Id as u32 {
                     let s = self.look.to_string();
-                    #[allow(unused_assignments)]
-                    let mut id: Option<Id> = None;
-
-                    match &self.look {
-                        Token::Word(word) => match &word {
-                            Word::Word(w) => {
-                                id = (*self.top.as_ref().unwrap()).get(&w);
-                            }
-                            _ => unreachable!(),
-                        },
+                    let span = self.span();
+                    let w = match self.look.clone() {
+                        Token::Word(Word::Word(w)) => w,
                         _ => unreachable!(),
-                    }
+                    };
+                    let symbol = self.resolve(&w, span);
 
-                    match id {
-                        None => self.error(&format!("{} undeclared", s)),
-                        _ => {}
-                    }
+                    let x: Box<dyn ExprAble> = match symbol {
+                        Some(Symbol::Var(id)) => Box::new(id),
+                        Some(Symbol::Const(p, value)) => {
+                            Box::new(Constant::new(Token::Num(Num::new(value as u64)), p))
+                        }
+                        Some(Symbol::Type(_)) => self.error(&format!("type {} used as a value", s)),
+                        Some(Symbol::Variant(p, _)) => self.error(&format!(
+                            "variant {} can only be assigned to a {} variable",
+                            s, p.word.lexeme
+                        )),
+                        None => self.undeclared(&w, span),
+                    };
                     self.move_();
-                    return Box::new(id.unwrap());
+                    x
                 } else {
                     self.error(&format!("{}", self.look.to_string()));
                 }
@@ -1,53 +1,372 @@
+use std::cell::Cell;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
 
 use crate::ir::*;
 use crate::lexer::*;
 
-pub struct Env {
-    table: HashMap<WordBase, Id>,
-    pub prev: Option<Box<Env>>,
+/// Symbol is what a name declared in a block stands for
+#[derive(Clone)]
+pub enum Symbol {
+    Var(Id),
+    Const(TypeBase, u32), // named constant of an enumeration
+    Type(TypeBase),
+    Variant(TypeBase, usize), // variant of a tagged union
 }
 
-impl Env {
-    pub fn new(n: Option<Box<Env>>) -> Env {
-        Env {
-            table: HashMap::new(),
-            prev: n,
+pub type ScopeId = usize;
+pub type SymbolId = usize;
+
+/// Scope is a block of a module, the imports of a module are a scope of their own around its
+/// outermost block
+pub struct Scope {
+    pub parent: Option<ScopeId>,
+    pub module: String,
+    pub names: HashMap<String, SymbolId>, // names declared or imported in the block
+}
+
+/// Declaration of a name, it stays in the tree after the block is parsed
+pub struct Declaration {
+    pub name: String,
+    pub symbol: Symbol,
+    pub scope: ScopeId,
+    pub span: Span,
+}
+
+/// Use of a name resolved to its declaration
+pub struct Reference {
+    pub symbol: SymbolId,
+    pub scope: ScopeId,
+    pub span: Span,
+}
+
+/// ScopeTree keeps the scopes of all modules of a program together with the declarations and
+/// uses of the names in them. Scopes and declarations are referred to by their index, an
+/// imported name refers to the declaration in the module that exports it.
+pub struct ScopeTree {
+    pub scopes: Vec<Scope>,
+    pub symbols: Vec<Declaration>,
+    pub references: Vec<Reference>,
+}
+
+impl Default for ScopeTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScopeTree {
+    pub fn new() -> ScopeTree {
+        ScopeTree {
+            scopes: Vec::new(),
+            symbols: Vec::new(),
+            references: Vec::new(),
         }
     }
 
-    pub fn put(&mut self, w: WordBase, i: Id) {
-        self.table.insert(w, i);
+    pub fn scope(&mut self, parent: Option<ScopeId>, module: &str) -> ScopeId {
+        self.scopes.push(Scope {
+            parent,
+            module: module.to_string(),
+            names: HashMap::new(),
+        });
+        self.scopes.len() - 1
+    }
+
+    /// Declares a name in a scope, the parser makes sure it is not declared there already
+    pub fn declare(
+        &mut self,
+        scope: ScopeId,
+        w: &WordBase,
+        symbol: Symbol,
+        span: Span,
+    ) -> SymbolId {
+        let s = self.symbols.len();
+        self.symbols.push(Declaration {
+            name: w.lexeme.clone(),
+            symbol,
+            scope,
+            span,
+        });
+        self.scopes[scope].names.insert(w.lexeme.clone(), s);
+        s
+    }
+
+    /// Makes a name declared elsewhere visible in a scope
+    pub fn import(&mut self, scope: ScopeId, name: &str, s: SymbolId) {
+        self.scopes[scope].names.insert(name.to_string(), s);
     }
 
-    pub fn get(&self, w: &WordBase) -> Option<Id> {
-        match self.table.get(w) {
-            Some(id) => {
-                return Some(id.clone());
+    pub fn refer(&mut self, s: SymbolId, scope: ScopeId, span: Span) {
+        self.references.push(Reference {
+            symbol: s,
+            scope,
+            span,
+        });
+    }
+
+    /// Places the variables of all modules in one frame and returns its size. The globals of the
+    /// modules come first, one module after another. The variables of a nested block follow
+    /// those of the enclosing blocks, and sibling blocks share their storage since they are
+    /// never active at the same time. Every variable is at its natural alignment.
+    pub fn lay_out(&self) -> u32 {
+        let mut children = vec![Vec::new(); self.scopes.len()];
+        let mut vars = vec![Vec::new(); self.scopes.len()];
+        for (i, scope) in self.scopes.iter().enumerate() {
+            if let Some(parent) = scope.parent {
+                children[parent].push(i);
             }
-            None => {}
-        };
-
-        let mut e = &(self.prev);
-        match e {
-            Some(ptr) => loop {
-                match (*ptr).table.get(w) {
-                    Some(id) => {
-                        return Some(id.clone());
-                    }
-                    None => {
-                        e = &(e.as_ref().unwrap().prev);
-                        match e {
-                            Some(_a) => continue,
-                            None => break,
-                        }
-                    }
+        }
+        for d in self.symbols.iter() {
+            if let Symbol::Var(id) = &d.symbol {
+                vars[d.scope].push(id);
+            }
+        }
+
+        // The outermost block of a module is the child of the scope of its imports
+        let modules: Vec<ScopeId> = self
+            .scopes
+            .iter()
+            .enumerate()
+            .filter(|(_, scope)| scope.parent.is_none())
+            .flat_map(|(i, _)| children[i].iter().copied())
+            .collect();
+        let mut globals = 0;
+        for scope in modules.iter() {
+            globals = place(&vars[*scope], globals);
+        }
+
+        let mut size = globals;
+        let mut work: Vec<(ScopeId, u32)> = modules
+            .iter()
+            .flat_map(|scope| children[*scope].iter().map(|c| (*c, globals)))
+            .collect();
+        while let Some((scope, start)) = work.pop() {
+            let end = place(&vars[scope], start);
+            size = size.max(end);
+            work.extend(children[scope].iter().map(|c| (*c, end)));
+        }
+        size
+    }
+
+    /// Number of blocks around a scope, the outermost block of a module is at depth 0
+    pub fn depth(&self, mut scope: ScopeId) -> u32 {
+        let mut depth = 0;
+        while let Some(parent) = self.scopes[scope].parent {
+            depth += 1;
+            scope = parent;
+        }
+        depth - 1
+    }
+
+    /// Symbol declared or used at a position of a module
+    pub fn symbol_at(&self, module: &str, line: u32, col: u32) -> Option<SymbolId> {
+        let declared = self.symbols.iter().enumerate().find_map(|(s, d)| {
+            (self.scopes[d.scope].module == module && d.span.contains(line, col)).then_some(s)
+        });
+        declared.or_else(|| {
+            self.references
+                .iter()
+                .find(|r| self.scopes[r.scope].module == module && r.span.contains(line, col))
+                .map(|r| r.symbol)
+        })
+    }
+
+    /// Uses of a symbol in the order they were parsed
+    pub fn references_to(&self, s: SymbolId) -> impl Iterator<Item = &Reference> {
+        self.references.iter().filter(move |r| r.symbol == s)
+    }
+}
+
+// Places variables one after another from start and returns the end of the last one
+fn place(vars: &[&Id], start: u32) -> u32 {
+    let mut end = start;
+    for id in vars {
+        let p = id.get_type();
+        let offset = align_to(end, p.align());
+        id.place(offset);
+        end = offset + p.get_width();
+    }
+    end
+}
+
+/// Number of characters to insert, delete or replace to turn a into b
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let replaced = diagonal + (x != *y) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = replaced.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Modules keeps the modules of a program. A module is the file <name>.dbook in the directory of
+/// the main file, what it exports is the scope of its outermost block. The modules being compiled
+/// form a chain of imports that is used to detect import cycles.
+pub struct Modules {
+    pub dir: PathBuf,
+    pub exports: HashMap<String, ScopeId>,
+    pub loading: Vec<String>,
+}
+
+impl Modules {
+    pub fn new(dir: PathBuf, main: &str) -> Modules {
+        Modules {
+            dir,
+            exports: HashMap::new(),
+            loading: vec![main.to_string()],
+        }
+    }
+}
+
+struct LabelScope {
+    parent: Option<usize>,
+    decl: Option<String>, // first variable declared in the block
+}
+
+struct LabelDef {
+    name: String,
+    scope: usize,
+    label: u32,
+}
+
+struct GotoRef {
+    name: String,
+    scope: usize,
+    line: u32,
+    target: Rc<Cell<u32>>,
+}
+
+/// LabelTable keeps the statement labels of a program in a tree of block scopes.
+/// A goto sees the labels of its own block, of the enclosing blocks and of the blocks
+/// nested in them, the innermost enclosing block that has a label with the name wins.
+pub struct LabelTable {
+    scopes: Vec<LabelScope>,
+    current: usize,
+    defs: Vec<LabelDef>,
+    gotos: Vec<GotoRef>,
+}
+
+impl Default for LabelTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LabelTable {
+    pub fn new() -> LabelTable {
+        LabelTable {
+            scopes: vec![LabelScope {
+                parent: None,
+                decl: None,
+            }],
+            current: 0,
+            defs: Vec::new(),
+            gotos: Vec::new(),
+        }
+    }
+
+    pub fn enter(&mut self) {
+        self.scopes.push(LabelScope {
+            parent: Some(self.current),
+            decl: None,
+        });
+        self.current = self.scopes.len() - 1;
+    }
+
+    pub fn leave(&mut self) {
+        self.current = self.scopes[self.current].parent.unwrap();
+    }
+
+    // Records a variable declared in the current block
+    pub fn declare(&mut self, w: &WordBase) {
+        let scope = &mut self.scopes[self.current];
+        if scope.decl.is_none() {
+            scope.decl = Some(w.lexeme.clone());
+        }
+    }
+
+    // A label may not be defined twice in a block or in blocks nested in each other
+    pub fn define(&mut self, w: &WordBase, label: u32, line: u32) {
+        let current = self.current;
+        if self.defs.iter().any(|d| {
+            d.name == w.lexeme
+                && (self.encloses(d.scope, current) || self.encloses(current, d.scope))
+        }) {
+            error(&format!("label {} already defined", w.lexeme), line);
+        }
+        self.defs.push(LabelDef {
+            name: w.lexeme.clone(),
+            scope: current,
+            label,
+        });
+    }
+
+    // Returns the target of a goto, it is set by resolve once all labels are known
+    pub fn goto(&mut self, w: &WordBase, line: u32) -> Rc<Cell<u32>> {
+        let target = Rc::new(Cell::new(0));
+        self.gotos.push(GotoRef {
+            name: w.lexeme.clone(),
+            scope: self.current,
+            line,
+            target: target.clone(),
+        });
+        target
+    }
+
+    // Is a the same block as b or a block enclosing it
+    fn encloses(&self, a: usize, b: usize) -> bool {
+        let mut s = Some(b);
+        while let Some(i) = s {
+            if i == a {
+                return true;
+            }
+            s = self.scopes[i].parent;
+        }
+        false
+    }
+
+    pub fn resolve(&self) {
+        for g in self.gotos.iter() {
+            let mut outer = Some(g.scope);
+            let (d, e) = loop {
+                let e = match outer {
+                    Some(e) => e,
+                    None => error(&format!("undefined label {}", g.name), g.line),
                 };
-            },
-            None => {
-                return None;
+                let mut found = self
+                    .defs
+                    .iter()
+                    .filter(|d| d.name == g.name && self.encloses(e, d.scope));
+                if let Some(d) = found.next() {
+                    if found.next().is_some() {
+                        error(&format!("label {} is ambiguous", g.name), g.line);
+                    }
+                    break (d, e);
+                }
+                outer = self.scopes[e].parent;
+            };
+
+            // Every block between the label and the common enclosing block is entered
+            // by the jump, so none of them may declare variables
+            let mut s = d.scope;
+            while s != e {
+                if let Some(x) = &self.scopes[s].decl {
+                    error(
+                        &format!("goto {} jumps past the declaration of {}", g.name, x),
+                        g.line,
+                    );
+                }
+                s = self.scopes[s].parent.unwrap();
             }
-        };
-        None
+            g.target.set(d.label);
+        }
     }
 }
@@ -0,0 +1,1494 @@
+// Hoare-style verifier: the annotated program is read as a command, verification conditions are
+// generated as weakest preconditions and every one of them is decided by a procedure for linear
+// integer arithmetic. Integers are unbounded here, overflow is not taken into account.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::ir::{error, StmtAble};
+use crate::lexer::Tag;
+use crate::symbols::SymbolId;
+
+/// Expression as the verifier sees it. Only int and bool expressions without side effects have
+/// a term other than Unsupported, which carries the text of the expression.
+pub enum Term {
+    Var(String, SymbolId, bool), // name, declaration and whether it is a bool
+    Num(u64),
+    Bool(bool),
+    Neg(Box<Term>),
+    Arith(char, Box<Term>, Box<Term>), // +, - or *
+    Rel(u32, Box<Term>, Box<Term>),    // tag of the comparison
+    And(Box<Term>, Box<Term>),
+    Or(Box<Term>, Box<Term>),
+    Not(Box<Term>),
+    Cond(Box<Term>, Box<Term>, Box<Term>),
+    Unsupported(String),
+}
+
+/// Statement as the verifier sees it, the line is where a problem with it is reported
+pub enum Command {
+    Skip,
+    Eval(Vec<Term>, u32),           // expressions evaluated for their value only
+    Assign(Vec<(Term, Term)>, u32), // the values are computed before any location is written
+    Havoc(Term, u32),               // the location gets an unknown value
+    Assert(Term, u32),
+    Seq(Box<Command>, Box<Command>),
+    If(Term, Box<Command>, Box<Command>, u32),
+    While {
+        cond: Term,
+        invariants: Vec<(Term, u32)>,
+        body: Box<Command>,
+        line: u32,
+    },
+    // requires is assumed on entry to the outermost block of a module and has to hold on entry
+    // to a nested one
+    Block {
+        requires: Vec<(Term, u32)>,
+        ensures: Vec<(Term, u32)>,
+        body: Box<Command>,
+        entry: bool,
+    },
+    Unsupported(String, u32, bool), // whether it may not be skipped, see annotated
+}
+
+type Var = usize;
+
+/// c1 * x1 + ... + cn * xn + constant, compared with zero in an atom
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Linear {
+    coeffs: BTreeMap<Var, i128>,
+    constant: i128,
+}
+
+impl Linear {
+    fn constant(c: i128) -> Linear {
+        Linear {
+            coeffs: BTreeMap::new(),
+            constant: c,
+        }
+    }
+
+    fn var(x: Var) -> Linear {
+        let mut l = Linear::constant(0);
+        l.coeffs.insert(x, 1);
+        l
+    }
+
+    fn coeff(&self, x: Var) -> i128 {
+        *self.coeffs.get(&x).unwrap_or(&0)
+    }
+
+    // self + k * other
+    fn add(&self, other: &Linear, k: i128) -> Linear {
+        let mut l = self.clone();
+        for (x, c) in other.coeffs.iter() {
+            let sum = l.coeff(*x) + k * c;
+            if sum == 0 {
+                l.coeffs.remove(x);
+            } else {
+                l.coeffs.insert(*x, sum);
+            }
+        }
+        l.constant += k * other.constant;
+        l
+    }
+
+    fn scale(&self, k: i128) -> Linear {
+        Linear::constant(0).add(self, k)
+    }
+
+    fn subst(&self, x: Var, e: &Linear) -> Linear {
+        let c = self.coeff(x);
+        if c == 0 {
+            return self.clone();
+        }
+        let mut l = self.clone();
+        l.coeffs.remove(&x);
+        l.add(e, c)
+    }
+}
+
+// Atoms are linear ≥ 0 and linear = 0, strict comparisons are tightened by one on integers
+#[derive(Clone)]
+enum Formula {
+    True,
+    False,
+    Var(Var),
+    Ge(Linear),
+    Eq(Linear),
+    Not(Box<Formula>),
+    And(Vec<Formula>),
+    Or(Vec<Formula>),
+}
+
+fn and(a: Formula, b: Formula) -> Formula {
+    match (a, b) {
+        (Formula::True, f) | (f, Formula::True) => f,
+        (Formula::False, _) | (_, Formula::False) => Formula::False,
+        (Formula::And(mut xs), Formula::And(ys)) => {
+            xs.extend(ys);
+            Formula::And(xs)
+        }
+        (Formula::And(mut xs), f) | (f, Formula::And(mut xs)) => {
+            xs.push(f);
+            Formula::And(xs)
+        }
+        (a, b) => Formula::And(vec![a, b]),
+    }
+}
+
+fn or(a: Formula, b: Formula) -> Formula {
+    match (a, b) {
+        (Formula::False, f) | (f, Formula::False) => f,
+        (Formula::True, _) | (_, Formula::True) => Formula::True,
+        (Formula::Or(mut xs), Formula::Or(ys)) => {
+            xs.extend(ys);
+            Formula::Or(xs)
+        }
+        (Formula::Or(mut xs), f) | (f, Formula::Or(mut xs)) => {
+            xs.push(f);
+            Formula::Or(xs)
+        }
+        (a, b) => Formula::Or(vec![a, b]),
+    }
+}
+
+fn not(f: Formula) -> Formula {
+    match f {
+        Formula::True => Formula::False,
+        Formula::False => Formula::True,
+        Formula::Not(f) => *f,
+        f => Formula::Not(Box::new(f)),
+    }
+}
+
+fn implies(a: Formula, b: Formula) -> Formula {
+    or(not(a), b)
+}
+
+fn iff(a: Formula, b: Formula) -> Formula {
+    or(and(a.clone(), b.clone()), and(not(a), not(b)))
+}
+
+/// Value substituted for a variable by an assignment. An int value may depend on conditions,
+/// x = c ? a : b gives x the value a when c holds and b otherwise.
+#[derive(Clone)]
+enum Value {
+    Int(Vec<(Formula, Linear)>),
+    Bool(Formula),
+}
+
+impl Formula {
+    fn subst(&self, values: &HashMap<Var, Value>) -> Formula {
+        match self {
+            Formula::Var(x) => match values.get(x) {
+                Some(Value::Bool(f)) => f.clone(),
+                _ => self.clone(),
+            },
+            Formula::Ge(l) => Formula::cases(l, values)
+                .into_iter()
+                .fold(Formula::False, |f, (g, l)| or(f, and(g, Formula::Ge(l)))),
+            Formula::Eq(l) => Formula::cases(l, values)
+                .into_iter()
+                .fold(Formula::False, |f, (g, l)| or(f, and(g, Formula::Eq(l)))),
+            Formula::Not(f) => not(f.subst(values)),
+            Formula::And(fs) => fs
+                .iter()
+                .fold(Formula::True, |a, f| and(a, f.subst(values))),
+            Formula::Or(fs) => fs
+                .iter()
+                .fold(Formula::False, |a, f| or(a, f.subst(values))),
+            _ => self.clone(),
+        }
+    }
+
+    // Substitutes all variables of l at once, there is one case for every combination of the
+    // conditional values
+    fn cases(l: &Linear, values: &HashMap<Var, Value>) -> Vec<(Formula, Linear)> {
+        let mut cases = vec![(Formula::True, Linear::constant(l.constant))];
+        for (x, c) in l.coeffs.iter() {
+            let of_x = match values.get(x) {
+                Some(Value::Int(of_x)) => of_x.clone(),
+                _ => vec![(Formula::True, Linear::var(*x))],
+            };
+            let mut next = Vec::new();
+            for (g1, l1) in cases.iter() {
+                for (g2, l2) in of_x.iter() {
+                    next.push((and(g1.clone(), g2.clone()), l1.add(l2, *c)));
+                }
+            }
+            cases = next;
+        }
+        cases
+    }
+
+    fn eval(&self, model: &Model) -> bool {
+        match self {
+            Formula::True => true,
+            Formula::False => false,
+            Formula::Var(x) => *model.bools.get(x).unwrap_or(&false),
+            Formula::Ge(l) => model.eval(l) >= 0,
+            Formula::Eq(l) => model.eval(l) == 0,
+            Formula::Not(f) => !f.eval(model),
+            Formula::And(fs) => fs.iter().all(|f| f.eval(model)),
+            Formula::Or(fs) => fs.iter().any(|f| f.eval(model)),
+        }
+    }
+
+    fn vars(&self, vars: &mut BTreeSet<Var>) {
+        match self {
+            Formula::Var(x) => {
+                vars.insert(*x);
+            }
+            Formula::Ge(l) | Formula::Eq(l) => vars.extend(l.coeffs.keys()),
+            Formula::Not(f) => f.vars(vars),
+            Formula::And(fs) | Formula::Or(fs) => {
+                for f in fs.iter() {
+                    f.vars(vars);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Value {
+    fn subst(&self, values: &HashMap<Var, Value>) -> Value {
+        match self {
+            Value::Bool(f) => Value::Bool(f.subst(values)),
+            Value::Int(cases) => {
+                let mut next = Vec::new();
+                for (g, l) in cases.iter() {
+                    let g = g.subst(values);
+                    for (g2, l) in Formula::cases(l, values) {
+                        next.push((and(g.clone(), g2), l));
+                    }
+                }
+                Value::Int(next)
+            }
+        }
+    }
+
+    fn show(&self, model: &Model) -> String {
+        match self {
+            Value::Bool(f) => f.eval(model).to_string(),
+            Value::Int(cases) => match cases.iter().find(|(g, _)| g.eval(model)) {
+                Some((_, l)) => model.eval(l).to_string(),
+                None => "?".to_string(),
+            },
+        }
+    }
+}
+
+/// Formula that has to hold in the initial state. state holds the variables of the annotation
+/// it comes from with their values at the annotation, so that a counterexample can show them.
+#[derive(Clone)]
+struct Goal {
+    id: usize,
+    formula: Formula,
+    state: Vec<(String, Value)>,
+    line: u32,
+    what: &'static str,
+}
+
+impl Goal {
+    fn subst(self, values: &HashMap<Var, Value>) -> Goal {
+        Goal {
+            formula: self.formula.subst(values),
+            state: self
+                .state
+                .iter()
+                .map(|(name, v)| (name.clone(), v.subst(values)))
+                .collect(),
+            ..self
+        }
+    }
+
+    fn assuming(self, f: Formula) -> Goal {
+        Goal {
+            formula: implies(f, self.formula),
+            ..self
+        }
+    }
+
+    // The goal g1 when x holds and g2 otherwise, both come from the same goal after an if
+    fn join(self, other: Goal, x: &Formula) -> Goal {
+        let state = self
+            .state
+            .into_iter()
+            .zip(other.state)
+            .map(|((name, v1), (_, v2))| {
+                let v = match (v1, v2) {
+                    (Value::Bool(f1), Value::Bool(f2)) => {
+                        Value::Bool(or(and(x.clone(), f1), and(not(x.clone()), f2)))
+                    }
+                    (Value::Int(c1), Value::Int(c2)) => Value::Int(
+                        c1.into_iter()
+                            .map(|(g, l)| (and(x.clone(), g), l))
+                            .chain(c2.into_iter().map(|(g, l)| (and(not(x.clone()), g), l)))
+                            .collect(),
+                    ),
+                    _ => unreachable!(),
+                };
+                (name, v)
+            })
+            .collect();
+        Goal {
+            formula: and(
+                implies(x.clone(), self.formula),
+                implies(not(x.clone()), other.formula),
+            ),
+            state,
+            ..self
+        }
+    }
+}
+
+struct Verifier {
+    // vars - variables of the program by their declaration
+    // names - names of all variables, fresh copies of a variable have its name
+    // bools - whether a variable is a bool
+    // goals - number of goals created
+    vars: HashMap<SymbolId, Var>,
+    names: Vec<String>,
+    bools: Vec<bool>,
+    goals: usize,
+}
+
+/// Whether a command contains annotations or a goto, wp looks at it even when nothing has to hold
+/// after it
+pub fn annotated(c: &Command) -> bool {
+    match c {
+        Command::Assert(..) | Command::Block { .. } => true,
+        Command::While {
+            invariants, body, ..
+        } => !invariants.is_empty() || annotated(body),
+        Command::Seq(a, b) | Command::If(_, a, b, _) => annotated(a) || annotated(b),
+        Command::Unsupported(_, _, annotations) => *annotations,
+        _ => false,
+    }
+}
+
+// Text of the first part of a term the verifier does not support
+fn unsupported(t: &Term) -> Option<&str> {
+    match t {
+        Term::Unsupported(s) => Some(s),
+        Term::Neg(a) | Term::Not(a) => unsupported(a),
+        Term::Arith(_, a, b) | Term::Rel(_, a, b) | Term::And(a, b) | Term::Or(a, b) => {
+            unsupported(a).or_else(|| unsupported(b))
+        }
+        Term::Cond(c, a, b) => unsupported(c)
+            .or_else(|| unsupported(a))
+            .or_else(|| unsupported(b)),
+        Term::Var(..) | Term::Num(_) | Term::Bool(_) => None,
+    }
+}
+
+fn is_bool(t: &Term) -> bool {
+    match t {
+        Term::Var(_, _, b) => *b,
+        Term::Bool(_) | Term::Rel(..) | Term::And(..) | Term::Or(..) | Term::Not(_) => true,
+        Term::Cond(_, a, _) => is_bool(a),
+        _ => false,
+    }
+}
+
+impl Verifier {
+    fn new() -> Verifier {
+        Verifier {
+            vars: HashMap::new(),
+            names: Vec::new(),
+            bools: Vec::new(),
+            goals: 0,
+        }
+    }
+
+    fn var(&mut self, name: &str, symbol: SymbolId, b: bool) -> Var {
+        if let Some(x) = self.vars.get(&symbol) {
+            return *x;
+        }
+        self.names.push(name.to_string());
+        self.bools.push(b);
+        self.vars.insert(symbol, self.names.len() - 1);
+        self.names.len() - 1
+    }
+
+    fn fresh(&mut self, x: Var) -> Var {
+        self.names.push(self.names[x].clone());
+        self.bools.push(self.bools[x]);
+        self.names.len() - 1
+    }
+
+    fn value_of(&self, x: Var) -> Value {
+        if self.bools[x] {
+            Value::Bool(Formula::Var(x))
+        } else {
+            Value::Int(vec![(Formula::True, Linear::var(x))])
+        }
+    }
+
+    fn goal(&mut self, formula: Formula, line: u32, what: &'static str) -> Goal {
+        let mut vars = BTreeSet::new();
+        formula.vars(&mut vars);
+        self.goals += 1;
+        Goal {
+            id: self.goals,
+            state: vars
+                .into_iter()
+                .map(|x| (self.names[x].clone(), self.value_of(x)))
+                .collect(),
+            formula,
+            line,
+            what,
+        }
+    }
+
+    fn int(&mut self, t: &Term) -> Result<Vec<(Formula, Linear)>, String> {
+        Ok(match t {
+            Term::Var(name, symbol, false) => {
+                vec![(Formula::True, Linear::var(self.var(name, *symbol, false)))]
+            }
+            Term::Num(v) => vec![(Formula::True, Linear::constant(*v as i128))],
+            Term::Neg(a) => self
+                .int(a)?
+                .into_iter()
+                .map(|(g, l)| (g, l.scale(-1)))
+                .collect(),
+            Term::Arith(op, a, b) => {
+                let (xs, ys) = (self.int(a)?, self.int(b)?);
+                let mut cases = Vec::new();
+                for (g1, l1) in xs.iter() {
+                    for (g2, l2) in ys.iter() {
+                        let l = match op {
+                            '+' => l1.add(l2, 1),
+                            '-' => l1.add(l2, -1),
+                            _ if l2.coeffs.is_empty() => l1.scale(l2.constant),
+                            _ if l1.coeffs.is_empty() => l2.scale(l1.constant),
+                            _ => return Err("a product of two variables".to_string()),
+                        };
+                        cases.push((and(g1.clone(), g2.clone()), l));
+                    }
+                }
+                cases
+            }
+            Term::Cond(c, a, b) => {
+                let c = self.formula(c)?;
+                let mut cases: Vec<(Formula, Linear)> = self
+                    .int(a)?
+                    .into_iter()
+                    .map(|(g, l)| (and(c.clone(), g), l))
+                    .collect();
+                for (g, l) in self.int(b)? {
+                    cases.push((and(not(c.clone()), g), l));
+                }
+                cases
+            }
+            Term::Unsupported(s) => return Err(s.clone()),
+            _ => return Err("a bool value used as an int".to_string()),
+        })
+    }
+
+    fn formula(&mut self, t: &Term) -> Result<Formula, String> {
+        Ok(match t {
+            Term::Bool(true) => Formula::True,
+            Term::Bool(false) => Formula::False,
+            Term::Var(name, symbol, true) => Formula::Var(self.var(name, *symbol, true)),
+            Term::And(a, b) => and(self.formula(a)?, self.formula(b)?),
+            Term::Or(a, b) => or(self.formula(a)?, self.formula(b)?),
+            Term::Not(a) => not(self.formula(a)?),
+            Term::Cond(c, a, b) => {
+                let c = self.formula(c)?;
+                or(
+                    and(c.clone(), self.formula(a)?),
+                    and(not(c), self.formula(b)?),
+                )
+            }
+            Term::Rel(tag, a, b) if is_bool(a) => {
+                let f = iff(self.formula(a)?, self.formula(b)?);
+                if *tag == Tag::Eq_ as u32 {
+                    f
+                } else if *tag == Tag::Ne as u32 {
+                    not(f)
+                } else {
+                    return Err("an ordering of bool values".to_string());
+                }
+            }
+            Term::Rel(tag, a, b) => {
+                let (xs, ys) = (self.int(a)?, self.int(b)?);
+                let mut f = Formula::False;
+                for (g1, l1) in xs.iter() {
+                    for (g2, l2) in ys.iter() {
+                        let l = l1.add(l2, -1);
+                        let atom = if *tag == '<' as u32 {
+                            Formula::Ge(l.scale(-1).add(&Linear::constant(1), -1))
+                        } else if *tag == '>' as u32 {
+                            Formula::Ge(l.add(&Linear::constant(1), -1))
+                        } else if *tag == Tag::Le as u32 {
+                            Formula::Ge(l.scale(-1))
+                        } else if *tag == Tag::Ge as u32 {
+                            Formula::Ge(l)
+                        } else if *tag == Tag::Eq_ as u32 {
+                            Formula::Eq(l)
+                        } else {
+                            not(Formula::Eq(l))
+                        };
+                        f = or(f, and(and(g1.clone(), g2.clone()), atom));
+                    }
+                }
+                f
+            }
+            Term::Unsupported(s) => return Err(s.clone()),
+            _ => return Err("an int value used as a bool".to_string()),
+        })
+    }
+
+    fn check<T>(r: Result<T, String>, line: u32) -> T {
+        match r {
+            Ok(x) => x,
+            Err(s) => error(&format!("cannot verify {}", s), line),
+        }
+    }
+
+    fn location(&mut self, t: &Term, line: u32) -> Var {
+        match t {
+            Term::Var(name, symbol, b) => self.var(name, *symbol, *b),
+            Term::Unsupported(s) => error(&format!("cannot verify {}", s), line),
+            _ => unreachable!(),
+        }
+    }
+
+    // Variables a command assigns. All of them have to be known, so the command may not have
+    // any part the verifier does not support: an expression that is not a term may write a
+    // variable, and a jump may leave a loop with its condition still true. The first such part
+    // is returned with its line.
+    fn modified(&mut self, c: &Command, vars: &mut Vec<Var>) -> Result<(), (String, u32)> {
+        let supported = |t: &Term, line: u32| match unsupported(t) {
+            Some(s) => Err((s.to_string(), line)),
+            None => Ok(()),
+        };
+        match c {
+            Command::Skip => {}
+            Command::Eval(ts, line) => {
+                for t in ts.iter() {
+                    supported(t, *line)?;
+                }
+            }
+            Command::Assign(pairs, line) => {
+                for (t, x) in pairs.iter() {
+                    supported(t, *line)?;
+                    supported(x, *line)?;
+                    vars.push(self.location(t, *line));
+                }
+            }
+            Command::Havoc(t, line) => {
+                supported(t, *line)?;
+                vars.push(self.location(t, *line));
+            }
+            Command::Assert(t, line) => supported(t, *line)?,
+            Command::Seq(a, b) => {
+                self.modified(a, vars)?;
+                self.modified(b, vars)?;
+            }
+            Command::If(x, a, b, line) => {
+                supported(x, *line)?;
+                self.modified(a, vars)?;
+                self.modified(b, vars)?;
+            }
+            Command::While {
+                cond,
+                invariants,
+                body,
+                line,
+            } => {
+                supported(cond, *line)?;
+                for (t, line) in invariants.iter() {
+                    supported(t, *line)?;
+                }
+                self.modified(body, vars)?;
+            }
+            Command::Block {
+                requires,
+                ensures,
+                body,
+                ..
+            } => {
+                for (t, line) in requires.iter().chain(ensures.iter()) {
+                    supported(t, *line)?;
+                }
+                self.modified(body, vars)?;
+            }
+            Command::Unsupported(s, line, _) => return Err((s.clone(), *line)),
+        }
+        Ok(())
+    }
+
+    fn annotations(&mut self, xs: &[(Term, u32)], what: &'static str) -> Vec<Goal> {
+        xs.iter()
+            .map(|(t, line)| {
+                let f = Verifier::check(self.formula(t), *line);
+                self.goal(f, *line, what)
+            })
+            .collect()
+    }
+
+    /// Weakest precondition of the goals in post. Code without annotations before which nothing
+    /// has to hold is not looked at, so it may use what the verifier does not support.
+    fn wp(&mut self, c: &Command, post: Vec<Goal>) -> Vec<Goal> {
+        if post.is_empty() && !annotated(c) {
+            return post;
+        }
+        match c {
+            Command::Skip => post,
+            Command::Eval(ts, line) => {
+                for t in ts.iter() {
+                    if is_bool(t) {
+                        Verifier::check(self.formula(t), *line);
+                    } else {
+                        Verifier::check(self.int(t), *line);
+                    }
+                }
+                post
+            }
+            Command::Assign(pairs, line) => {
+                // A value with several cases is given to a fresh variable that equals the value
+                // of the case that holds. Substituted as it is, the cases of consecutive
+                // conditional assignments would multiply.
+                let mut values = HashMap::new();
+                let mut defs = Formula::True;
+                for (t, x) in pairs.iter() {
+                    let v = self.location(t, *line);
+                    let value = if self.bools[v] {
+                        Value::Bool(Verifier::check(self.formula(x), *line))
+                    } else {
+                        let cases = Verifier::check(self.int(x), *line);
+                        if cases.len() == 1 {
+                            Value::Int(cases)
+                        } else {
+                            let y = self.fresh(v);
+                            for (g, l) in cases {
+                                let eq = Formula::Eq(Linear::var(y).add(&l, -1));
+                                defs = and(defs, implies(g, eq));
+                            }
+                            self.value_of(y)
+                        }
+                    };
+                    values.insert(v, value);
+                }
+                post.into_iter()
+                    .map(|g| g.subst(&values).assuming(defs.clone()))
+                    .collect()
+            }
+            Command::Havoc(t, line) => {
+                let v = self.location(t, *line);
+                let x = self.fresh(v);
+                let mut values = HashMap::new();
+                values.insert(v, self.value_of(x));
+                post.into_iter().map(|g| g.subst(&values)).collect()
+            }
+            Command::Assert(t, line) => {
+                let f = Verifier::check(self.formula(t), *line);
+                let mut goals = vec![self.goal(f, *line, "assertion may not hold")];
+                goals.extend(post);
+                goals
+            }
+            Command::Seq(a, b) => {
+                let post = self.wp(b, post);
+                self.wp(a, post)
+            }
+            Command::If(x, a, b, line) => {
+                // A goal that comes through both branches stays one
+                let x = Verifier::check(self.formula(x), *line);
+                let mut other = self.wp(b, post.clone());
+                let mut goals = Vec::new();
+                for g in self.wp(a, post) {
+                    match other.iter().position(|h| h.id == g.id) {
+                        Some(k) => goals.push(g.join(other.remove(k), &x)),
+                        None => goals.push(g.assuming(x.clone())),
+                    }
+                }
+                for h in other {
+                    goals.push(h.assuming(not(x.clone())));
+                }
+                goals
+            }
+            Command::While {
+                cond,
+                invariants,
+                body,
+                line,
+            } => {
+                // The goals about the loop hold for any values of the variables it modifies, those
+                // are replaced by fresh ones
+                let x = Verifier::check(self.formula(cond), *line);
+                let entry = self.annotations(invariants, "loop invariant may not hold on entry");
+                let i = entry
+                    .iter()
+                    .fold(Formula::True, |i, g| and(i, g.formula.clone()));
+                let preserved = self.annotations(invariants, "loop invariant may not be preserved");
+                let preserved = self.wp(body, preserved);
+
+                let mut modified = Vec::new();
+                if let Err((s, line)) = self.modified(body, &mut modified) {
+                    error(&format!("cannot verify {}", s), line);
+                }
+                let mut values = HashMap::new();
+                for v in modified {
+                    let x = self.fresh(v);
+                    values.insert(v, self.value_of(x));
+                }
+
+                let mut goals = entry;
+                for g in preserved {
+                    goals.push(g.assuming(and(i.clone(), x.clone())).subst(&values));
+                }
+                for g in post {
+                    goals.push(g.assuming(and(i.clone(), not(x.clone()))).subst(&values));
+                }
+                goals
+            }
+            Command::Block {
+                requires,
+                ensures,
+                body,
+                entry,
+            } => {
+                let mut exit = self.annotations(ensures, "postcondition may not hold");
+                exit.extend(post);
+                let required = self.annotations(requires, "precondition may not hold");
+                let p = required
+                    .iter()
+                    .fold(Formula::True, |p, g| and(p, g.formula.clone()));
+
+                let mut goals = if *entry { Vec::new() } else { required };
+                for g in self.wp(body, exit) {
+                    goals.push(g.assuming(p.clone()));
+                }
+                goals
+            }
+            Command::Unsupported(s, line, _) => error(&format!("cannot verify {}", s), *line),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct Model {
+    ints: HashMap<Var, i128>,
+    bools: HashMap<Var, bool>,
+}
+
+impl Model {
+    fn eval(&self, l: &Linear) -> i128 {
+        l.coeffs.iter().fold(l.constant, |v, (x, c)| {
+            v + c * self.ints.get(x).unwrap_or(&0)
+        })
+    }
+}
+
+enum Answer {
+    Sat(Model),
+    Unsat,
+    Unknown,
+}
+
+// Constraints are linear ≥ 0 or, when the flag is set, linear = 0
+type Constraint = (Linear, bool);
+
+// Limits of the integer search, beyond them a condition is reported as undecided
+const MAX_DEPTH: u32 = 32;
+const MAX_CONSTRAINTS: usize = 5000;
+
+// Looks for an assignment that makes the formulas in todo hold with the given polarities. The
+// conjuncts are collected first, then the disjunctions are decided one alternative at a time as
+// long as the constraints collected so far can be satisfied.
+fn search<'a>(
+    mut todo: Vec<(&'a Formula, bool)>,
+    mut branches: Vec<(&'a Formula, bool)>,
+    mut bools: HashMap<Var, bool>,
+    mut cons: Vec<Constraint>,
+) -> Answer {
+    while let Some((f, positive)) = todo.pop() {
+        match (f, positive) {
+            (Formula::True, false) | (Formula::False, true) => return Answer::Unsat,
+            (Formula::True, true) | (Formula::False, false) => {}
+            (Formula::Var(x), b) => {
+                if *bools.entry(*x).or_insert(b) != b {
+                    return Answer::Unsat;
+                }
+            }
+            (Formula::Ge(l), true) => cons.push((l.clone(), false)),
+            (Formula::Ge(l), false) => {
+                cons.push((l.scale(-1).add(&Linear::constant(1), -1), false))
+            }
+            (Formula::Eq(l), true) => cons.push((l.clone(), true)),
+            (Formula::Not(f), b) => todo.push((f, !b)),
+            (Formula::And(fs), true) | (Formula::Or(fs), false) => {
+                todo.extend(fs.iter().map(|f| (f, positive)))
+            }
+            _ => branches.push((f, positive)),
+        }
+    }
+
+    let (f, positive) = match branches.pop() {
+        Some(branch) => branch,
+        None => {
+            return match solve(cons, 0) {
+                Answer::Sat(mut m) => {
+                    m.bools = bools;
+                    Answer::Sat(m)
+                }
+                a => a,
+            }
+        }
+    };
+    if let Answer::Unsat = solve(cons.clone(), 0) {
+        return Answer::Unsat;
+    }
+
+    let mut unknown = false;
+    let mut decide = |a: Answer| match a {
+        Answer::Sat(m) => Some(m),
+        Answer::Unknown => {
+            unknown = true;
+            None
+        }
+        Answer::Unsat => None,
+    };
+    match f {
+        // l ≠ 0 is l < 0 or l > 0
+        Formula::Eq(l) => {
+            for k in [-1, 1] {
+                let mut cons = cons.clone();
+                cons.push((l.scale(k).add(&Linear::constant(1), -1), false));
+                if let Some(m) = decide(search(vec![], branches.clone(), bools.clone(), cons)) {
+                    return Answer::Sat(m);
+                }
+            }
+        }
+        Formula::And(fs) | Formula::Or(fs) => {
+            for f in fs.iter() {
+                let todo = vec![(f, positive)];
+                let a = search(todo, branches.clone(), bools.clone(), cons.clone());
+                if let Some(m) = decide(a) {
+                    return Answer::Sat(m);
+                }
+            }
+        }
+        _ => unreachable!(),
+    }
+    if unknown {
+        Answer::Unknown
+    } else {
+        Answer::Unsat
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// Divides a constraint by the gcd of its coefficients, the constant of an inequality is rounded
+// down as only integer solutions count. None when the constraint has no integer solution.
+fn normalize((l, eq): Constraint) -> Option<Constraint> {
+    if l.coeffs.is_empty() {
+        let holds = if eq { l.constant == 0 } else { l.constant >= 0 };
+        return if holds { Some((l, eq)) } else { None };
+    }
+    let g = l.coeffs.values().fold(0, |g, c| gcd(g, *c));
+    if eq && l.constant % g != 0 {
+        return None;
+    }
+    let coeffs = l.coeffs.iter().map(|(x, c)| (*x, c / g)).collect();
+    let constant = l.constant.div_euclid(g);
+    Some((Linear { coeffs, constant }, eq))
+}
+
+/// Rational number with a positive denominator in lowest terms
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Rat {
+    num: i128,
+    den: i128,
+}
+
+impl Rat {
+    fn new(num: i128, den: i128) -> Rat {
+        let g = gcd(num, den).max(1) * den.signum();
+        Rat {
+            num: num / g,
+            den: den / g,
+        }
+    }
+
+    fn int(v: i128) -> Rat {
+        Rat { num: v, den: 1 }
+    }
+
+    fn floor(&self) -> i128 {
+        self.num.div_euclid(self.den)
+    }
+
+    fn ceil(&self) -> i128 {
+        -(-self.num).div_euclid(self.den)
+    }
+}
+
+impl PartialOrd for Rat {
+    fn partial_cmp(&self, other: &Rat) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rat {
+    fn cmp(&self, other: &Rat) -> Ordering {
+        (self.num * other.den).cmp(&(other.num * self.den))
+    }
+}
+
+// Value of l with x left out, variables without a value are zero
+fn eval(l: &Linear, x: Var, values: &HashMap<Var, Rat>) -> Rat {
+    l.coeffs
+        .iter()
+        .filter(|(y, _)| **y != x)
+        .fold(Rat::int(l.constant), |r, (y, c)| {
+            let v = values.get(y).copied().unwrap_or(Rat::int(0));
+            Rat::new(r.num * v.den + c * v.num * r.den, r.den * v.den)
+        })
+}
+
+// Integer solution of the constraints: equalities with a coefficient of one are solved for that
+// variable, the inequalities are projected by Fourier-Motzkin elimination and the solution is
+// built back variable by variable. A variable whose bounds leave no room for an integer is
+// branched on.
+fn solve(cons: Vec<Constraint>, depth: u32) -> Answer {
+    let mut eqs = Vec::new();
+    let mut system = BTreeSet::new();
+    for c in cons.iter() {
+        match normalize(c.clone()) {
+            None => return Answer::Unsat,
+            Some((l, true)) => eqs.push(l),
+            Some((l, false)) => {
+                system.insert(l);
+            }
+        }
+    }
+
+    let mut defs: Vec<(Var, Linear)> = Vec::new();
+    while let Some(l) = eqs.pop() {
+        let l = match normalize((l, true)) {
+            Some((l, _)) => l,
+            None => return Answer::Unsat,
+        };
+        let unit = l.coeffs.iter().find(|(_, c)| c.abs() == 1);
+        match unit {
+            Some((&x, &c)) => {
+                let mut e = l.clone();
+                e.coeffs.remove(&x);
+                let e = e.scale(-c);
+                for q in eqs.iter_mut() {
+                    *q = q.subst(x, &e);
+                }
+                for d in defs.iter_mut() {
+                    d.1 = d.1.subst(x, &e);
+                }
+                let mut next = BTreeSet::new();
+                for q in system.iter() {
+                    match normalize((q.subst(x, &e), false)) {
+                        None => return Answer::Unsat,
+                        Some((q, _)) => {
+                            next.insert(q);
+                        }
+                    }
+                }
+                system = next;
+                defs.push((x, e));
+            }
+            None if l.coeffs.is_empty() => {}
+            None => {
+                system.insert(l.scale(-1));
+                system.insert(l);
+            }
+        }
+    }
+
+    let mut vars = BTreeSet::new();
+    for l in system.iter() {
+        vars.extend(l.coeffs.keys());
+    }
+    let mut eliminated = Vec::new();
+    for x in vars {
+        let (bounds, rest): (Vec<Linear>, Vec<Linear>) =
+            system.into_iter().partition(|l| l.coeff(x) != 0);
+        system = rest.into_iter().collect();
+        for lower in bounds.iter().filter(|l| l.coeff(x) > 0) {
+            for upper in bounds.iter().filter(|l| l.coeff(x) < 0) {
+                let l = lower.scale(-upper.coeff(x)).add(upper, lower.coeff(x));
+                match normalize((l, false)) {
+                    None => return Answer::Unsat,
+                    Some((l, _)) if l.coeffs.is_empty() => {}
+                    Some((l, _)) => {
+                        system.insert(l);
+                    }
+                }
+            }
+        }
+        if system.len() > MAX_CONSTRAINTS {
+            return Answer::Unknown;
+        }
+        eliminated.push((x, bounds));
+    }
+
+    // Every variable takes the value closest to zero within its bounds
+    let mut values: HashMap<Var, Rat> = HashMap::new();
+    for (x, bounds) in eliminated.iter().rev() {
+        let (mut low, mut high): (Option<Rat>, Option<Rat>) = (None, None);
+        for l in bounds.iter() {
+            let c = l.coeff(*x);
+            let r = eval(l, *x, &values);
+            if c > 0 {
+                let b = Rat::new(-r.num, r.den * c);
+                low = Some(low.map_or(b, |low| low.max(b)));
+            } else {
+                let b = Rat::new(r.num, r.den * -c);
+                high = Some(high.map_or(b, |high| high.min(b)));
+            }
+        }
+        let v = match (low, high) {
+            (None, None) => Rat::int(0),
+            (Some(low), None) => Rat::int(low.ceil().max(0)),
+            (None, Some(high)) => Rat::int(high.floor().min(0)),
+            (Some(low), Some(high)) if low.ceil() <= high.floor() => {
+                Rat::int(0.clamp(low.ceil(), high.floor()))
+            }
+            (Some(low), Some(_)) => low,
+        };
+        values.insert(*x, v);
+    }
+
+    if let Some((x, v)) = values.iter().find(|(_, v)| v.den != 1) {
+        if depth == MAX_DEPTH {
+            return Answer::Unknown;
+        }
+        let mut unknown = false;
+        for bound in [
+            Linear::constant(v.floor()).add(&Linear::var(*x), -1),
+            Linear::var(*x).add(&Linear::constant(v.ceil()), -1),
+        ] {
+            let mut cons = cons.clone();
+            cons.push((bound, false));
+            match solve(cons, depth + 1) {
+                Answer::Sat(m) => return Answer::Sat(m),
+                Answer::Unknown => unknown = true,
+                Answer::Unsat => {}
+            }
+        }
+        return if unknown {
+            Answer::Unknown
+        } else {
+            Answer::Unsat
+        };
+    }
+
+    let mut model = Model::default();
+    for (x, v) in values.iter() {
+        model.ints.insert(*x, v.num);
+    }
+    for (x, e) in defs.iter().rev() {
+        let v = eval(e, usize::MAX, &values);
+        values.insert(*x, v);
+        model.ints.insert(*x, v.num);
+    }
+    Answer::Sat(model)
+}
+
+/// Checks the annotations of a module and prints every condition that may not hold with a
+/// counterexample
+pub fn verify(s: &dyn StmtAble, module: &str) {
+    let conditions = conditions(&s.command());
+    let mut failed = 0;
+    for (line, what, failure) in conditions.iter() {
+        if let Some(s) = failure {
+            println!("near line {}: {}{}", line, what, s);
+            failed += 1;
+        }
+    }
+    println!(
+        "{}: {} of {} verification conditions hold, assuming int arithmetic does not overflow",
+        module,
+        conditions.len() - failed,
+        conditions.len()
+    );
+}
+
+// Verification conditions of a command in the order of their lines, each with the reason it may
+// not hold if it may not
+fn conditions(c: &Command) -> Vec<(u32, &'static str, Option<String>)> {
+    let mut v = Verifier::new();
+    let mut goals = v.wp(c, Vec::new());
+    goals.sort_by_key(|g| (g.line, g.what));
+
+    // Goals from the same annotation are reported as one condition
+    let mut conditions: Vec<(u32, &'static str, Option<String>)> = Vec::new();
+    for g in goals.iter() {
+        if conditions
+            .last()
+            .is_none_or(|(line, what, _)| *line != g.line || *what != g.what)
+        {
+            conditions.push((g.line, g.what, None));
+        }
+        let last = conditions.last_mut().unwrap();
+        if last.2.is_some() {
+            continue;
+        }
+        last.2 = match search(
+            vec![(&g.formula, false)],
+            Vec::new(),
+            HashMap::new(),
+            Vec::new(),
+        ) {
+            Answer::Unsat => None,
+            Answer::Sat(_) if g.state.is_empty() => Some(String::new()),
+            Answer::Sat(model) => {
+                let values: Vec<String> = g
+                    .state
+                    .iter()
+                    .map(|(name, v)| format!("{} = {}", name, v.show(&model)))
+                    .collect();
+                Some(format!(", counterexample: {}", values.join(", ")))
+            }
+            Answer::Unknown => Some(", it could not be decided".to_string()),
+        };
+    }
+    conditions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(name: &str, symbol: SymbolId) -> Term {
+        Term::Var(name.to_string(), symbol, false)
+    }
+
+    fn arith(op: char, a: Term, b: Term) -> Term {
+        Term::Arith(op, Box::new(a), Box::new(b))
+    }
+
+    fn rel(tag: u32, a: Term, b: Term) -> Term {
+        Term::Rel(tag, Box::new(a), Box::new(b))
+    }
+
+    fn seq(cs: Vec<Command>) -> Command {
+        cs.into_iter()
+            .reduce(|a, b| Command::Seq(Box::new(a), Box::new(b)))
+            .unwrap()
+    }
+
+    fn block(body: Command, requires: Vec<(Term, u32)>) -> Command {
+        Command::Block {
+            requires,
+            ensures: Vec::new(),
+            body: Box::new(body),
+            entry: true,
+        }
+    }
+
+    // c1 * x1 + ... + constant
+    fn linear(coeffs: &[(Var, i128)], constant: i128) -> Linear {
+        coeffs.iter().fold(Linear::constant(constant), |l, (x, c)| {
+            l.add(&Linear::var(*x), *c)
+        })
+    }
+
+    fn holds(cons: &[Constraint], m: &Model) -> bool {
+        cons.iter().all(|(l, eq)| {
+            let v = m.eval(l);
+            if *eq {
+                v == 0
+            } else {
+                v >= 0
+            }
+        })
+    }
+
+    #[test]
+    fn rat() {
+        assert!(Rat::new(4, -6) == Rat { num: -2, den: 3 });
+        assert_eq!(Rat::new(-3, 2).floor(), -2);
+        assert_eq!(Rat::new(-3, 2).ceil(), -1);
+        assert_eq!(Rat::new(7, 2).floor(), 3);
+        assert_eq!(Rat::new(7, 2).ceil(), 4);
+        assert_eq!(Rat::new(0, 5).ceil(), 0);
+        assert!(Rat::new(1, 3) < Rat::new(1, 2));
+        assert!(Rat::new(-1, 2) < Rat::new(-1, 3));
+    }
+
+    #[test]
+    fn normalize_rounds_to_integers() {
+        // 2x - 1 ≥ 0 is x - 1 ≥ 0 on integers
+        let (l, _) = normalize((linear(&[(0, 2)], -1), false)).unwrap();
+        assert!(l == linear(&[(0, 1)], -1));
+        // 2x + 4y = 1 has no integer solution
+        assert!(normalize((linear(&[(0, 2), (1, 4)], -1), true)).is_none());
+        assert!(normalize((Linear::constant(-1), false)).is_none());
+    }
+
+    #[test]
+    fn solve_sat() {
+        // 1 ≤ x ≤ 3, y = x + 2
+        let cons = vec![
+            (linear(&[(0, 1)], -1), false),
+            (linear(&[(0, -1)], 3), false),
+            (linear(&[(1, 1), (0, -1)], -2), true),
+        ];
+        match solve(cons.clone(), 0) {
+            Answer::Sat(m) => assert!(holds(&cons, &m)),
+            _ => panic!("expected a solution"),
+        }
+    }
+
+    #[test]
+    fn solve_unsat() {
+        // x ≥ 3 and x ≤ 1
+        let cons = vec![
+            (linear(&[(0, 1)], -3), false),
+            (linear(&[(0, -1)], 1), false),
+        ];
+        assert!(matches!(solve(cons, 0), Answer::Unsat));
+    }
+
+    #[test]
+    fn solve_integer_gap() {
+        // 1 ≤ 2x - 2y ≤ 1 has rational solutions only
+        let cons = vec![
+            (linear(&[(0, 2), (1, -2)], -1), false),
+            (linear(&[(0, -2), (1, 2)], 1), false),
+        ];
+        assert!(matches!(solve(cons, 0), Answer::Unsat));
+
+        // A triangle with rational points only, every constraint is normalized already so x or
+        // y has to be branched on
+        let cons = vec![
+            (linear(&[(0, 4), (1, 1)], 4), false),
+            (linear(&[(0, -4), (1, 1)], 1), false),
+            (linear(&[(0, -2), (1, -3)], -6), false),
+        ];
+        assert!(matches!(solve(cons, 0), Answer::Unsat));
+
+        // 3x + 2y = 7 and y = 2, the equalities are solved for x = 1
+        let cons = vec![
+            (linear(&[(0, 3), (1, 2)], -7), true),
+            (linear(&[(1, 1)], -2), true),
+        ];
+        match solve(cons.clone(), 0) {
+            Answer::Sat(m) => {
+                assert!(holds(&cons, &m));
+                assert_eq!(m.ints.get(&0), Some(&1));
+            }
+            _ => panic!("expected a solution"),
+        }
+    }
+
+    // Small systems in a box are compared with an exhaustive search
+    #[test]
+    fn solve_agrees_with_enumeration() {
+        let mut seed: u64 = 12345;
+        let mut next = |n: i128| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as i128 % n
+        };
+        for _ in 0..300 {
+            let mut cons = Vec::new();
+            for x in 0..3 {
+                cons.push((linear(&[(x, 1)], 4), false));
+                cons.push((linear(&[(x, -1)], 4), false));
+            }
+            for _ in 0..3 {
+                let coeffs: Vec<(Var, i128)> = (0..3).map(|x| (x, next(7) - 3)).collect();
+                cons.push((linear(&coeffs, next(13) - 6), next(4) == 0));
+            }
+
+            let mut found = false;
+            for x in -4..=4 {
+                for y in -4..=4 {
+                    for z in -4..=4 {
+                        let mut m = Model::default();
+                        m.ints.extend([(0, x), (1, y), (2, z)]);
+                        found |= holds(&cons, &m);
+                    }
+                }
+            }
+            match solve(cons.clone(), 0) {
+                Answer::Sat(m) => assert!(found && holds(&cons, &m)),
+                Answer::Unsat => assert!(!found),
+                Answer::Unknown => panic!("undecided"),
+            }
+        }
+    }
+
+    #[test]
+    fn search_branches_on_disjunctions() {
+        // x = 0 and (x ≥ 5 or x ≤ -5)
+        let x = Linear::var(0);
+        let f = Formula::And(vec![
+            Formula::Eq(x.clone()),
+            Formula::Or(vec![
+                Formula::Ge(x.add(&Linear::constant(5), -1)),
+                Formula::Ge(x.scale(-1).add(&Linear::constant(5), -1)),
+            ]),
+        ]);
+        let search_for = |f: &Formula| search(vec![(f, true)], vec![], HashMap::new(), vec![]);
+        assert!(matches!(search_for(&f), Answer::Unsat));
+
+        // x ≠ 0 and 0 ≤ x ≤ 1
+        let f = Formula::And(vec![
+            not(Formula::Eq(x.clone())),
+            Formula::Ge(x.clone()),
+            Formula::Ge(x.scale(-1).add(&Linear::constant(1), 1)),
+        ]);
+        match search_for(&f) {
+            Answer::Sat(m) => assert_eq!(m.ints.get(&0), Some(&1)),
+            _ => panic!("expected a solution"),
+        }
+    }
+
+    // requires(n >= 0); i = 0; while (i < n) invariant(i <= n) { i = i + 1; } assert(i == n);
+    fn counting_loop(invariant: Term) -> Command {
+        let (i, n) = (|| int("i", 0), || int("n", 1));
+        block(
+            seq(vec![
+                Command::Assign(vec![(i(), Term::Num(0))], 3),
+                Command::While {
+                    cond: rel('<' as u32, i(), n()),
+                    invariants: vec![(invariant, 4)],
+                    body: Box::new(Command::Assign(
+                        vec![(i(), arith('+', i(), Term::Num(1)))],
+                        5,
+                    )),
+                    line: 4,
+                },
+                Command::Assert(rel(Tag::Eq_ as u32, i(), n()), 7),
+            ]),
+            vec![(rel(Tag::Ge as u32, n(), Term::Num(0)), 2)],
+        )
+    }
+
+    #[test]
+    fn loop_invariant_proves_assertion() {
+        let c = counting_loop(rel(Tag::Le as u32, int("i", 0), int("n", 1)));
+        let conditions = conditions(&c);
+        assert_eq!(conditions.len(), 3);
+        assert!(conditions.iter().all(|(_, _, failure)| failure.is_none()));
+    }
+
+    #[test]
+    fn weak_invariant_gives_counterexample() {
+        let c = counting_loop(Term::Bool(true));
+        let conditions = conditions(&c);
+        let failed: Vec<_> = conditions
+            .iter()
+            .filter_map(|(line, what, failure)| failure.as_ref().map(|f| (*line, *what, f)))
+            .collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, 7);
+        assert_eq!(failed[0].1, "assertion may not hold");
+        assert!(failed[0].2.starts_with(", counterexample: "));
+    }
+
+    // x = 0; x = x < y ? x + 1 : y; ... assert(x <= y); with y ≥ 0
+    #[test]
+    fn conditional_assignments_stay_small() {
+        let (x, y) = (|| int("x", 0), || int("y", 1));
+        let mut cs = vec![Command::Assign(vec![(x(), Term::Num(0))], 3)];
+        for line in 4..40 {
+            let value = Term::Cond(
+                Box::new(rel('<' as u32, x(), y())),
+                Box::new(arith('+', x(), Term::Num(1))),
+                Box::new(y()),
+            );
+            cs.push(Command::Assign(vec![(x(), value)], line));
+        }
+        cs.push(Command::Assert(rel(Tag::Le as u32, x(), y()), 40));
+        let c = block(seq(cs), vec![(rel(Tag::Ge as u32, y(), Term::Num(0)), 2)]);
+        let conditions = conditions(&c);
+        assert_eq!(conditions.len(), 1);
+        assert!(conditions[0].2.is_none());
+    }
+
+    // b = 0; i = 0; while (i < n) { a = b = 3; i++; } assert(b == 0);
+    #[test]
+    fn nested_write_in_loop_is_rejected() {
+        let (a, b, i, n) = (
+            || int("a", 0),
+            || int("b", 1),
+            || int("i", 2),
+            || int("n", 3),
+        );
+        let body = seq(vec![
+            Command::Assign(vec![(a(), Term::Unsupported("b = 3".to_string()))], 4),
+            Command::Assign(vec![(i(), arith('+', i(), Term::Num(1)))], 4),
+        ]);
+        let mut v = Verifier::new();
+        let mut vars = Vec::new();
+        assert_eq!(v.modified(&body, &mut vars), Err(("b = 3".to_string(), 4)));
+
+        // Written directly, b is modified by the loop and the assertion fails
+        let c = block(
+            seq(vec![
+                Command::Assign(vec![(b(), Term::Num(0))], 3),
+                Command::Assign(vec![(i(), Term::Num(0))], 3),
+                Command::While {
+                    cond: rel('<' as u32, i(), n()),
+                    invariants: Vec::new(),
+                    body: Box::new(seq(vec![
+                        Command::Assign(vec![(b(), Term::Num(3))], 4),
+                        Command::Assign(vec![(i(), arith('+', i(), Term::Num(1)))], 4),
+                    ])),
+                    line: 4,
+                },
+                Command::Assert(rel(Tag::Eq_ as u32, b(), Term::Num(0)), 5),
+            ]),
+            Vec::new(),
+        );
+        let conditions = conditions(&c);
+        assert_eq!(conditions.len(), 1);
+        assert!(conditions[0].2.is_some());
+    }
+
+    // requires(n > 0); i = 0; while (i < n) { break; } assert(i >= n);
+    #[test]
+    fn break_in_loop_is_rejected() {
+        let body = seq(vec![
+            Command::Skip,
+            Command::Unsupported("break".to_string(), 5, false),
+        ]);
+        let mut v = Verifier::new();
+        let mut vars = Vec::new();
+        assert_eq!(v.modified(&body, &mut vars), Err(("break".to_string(), 5)));
+    }
+
+    // x = 1; match (s) { ... assert(x == 2); ... } and l: x = x + 1; goto l; are not skipped
+    #[test]
+    fn annotated_unsupported_is_not_skipped() {
+        let x = || int("x", 0);
+        let c = seq(vec![
+            Command::Assign(vec![(x(), Term::Num(1))], 3),
+            Command::Unsupported("match".to_string(), 4, true),
+        ]);
+        assert!(annotated(&c));
+        let c = seq(vec![
+            Command::Assign(vec![(x(), arith('+', x(), Term::Num(1)))], 3),
+            Command::Unsupported("goto".to_string(), 4, true),
+        ]);
+        assert!(annotated(&c));
+        assert!(!annotated(&Command::Unsupported(
+            "break".to_string(),
+            5,
+            false
+        )));
+    }
+}